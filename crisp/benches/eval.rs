@@ -0,0 +1,42 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use crisp::{parse_and_eval, Context};
+
+const DEFINITIONS: &str = "
+(let (sum x acc) (if (> x 0) (sum (- x 1) (+ acc x)) acc))
+(let (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))
+(let (second xs) (car (cdr xs)))
+(let big (range 10000))";
+
+fn context() -> Context {
+    let mut context = Context::default();
+    parse_and_eval(DEFINITIONS, &mut context).unwrap().unwrap();
+    context
+}
+
+fn bench(c: &mut Criterion, name: &str, input: &str) {
+    let mut context = context();
+    let exprs = crisp::parse(input).unwrap();
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            for expr in exprs.clone() {
+                context.eval(expr).unwrap();
+            }
+        })
+    });
+}
+
+fn eval(c: &mut Criterion) {
+    bench(c, "sum 1000", "(sum 1000 0)");
+    bench(c, "fib 15", "(fib 15)");
+    // Passing a large list around shouldn't copy it
+    bench(c, "pass 10k list", "(second big) (second big) (second big)");
+    bench(c, "map 10k list", "(reduce + (map (fn (x) (* x 2)) big))");
+}
+
+fn parse(c: &mut Criterion) {
+    let input = DEFINITIONS.repeat(100);
+    c.bench_function("parse", |b| b.iter(|| crisp::parse(&input).unwrap()));
+}
+
+criterion_group!(benches, eval, parse);
+criterion_main!(benches);
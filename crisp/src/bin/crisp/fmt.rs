@@ -0,0 +1,60 @@
+use std::io::Read;
+use std::process::ExitCode;
+
+// With --check nothing is written, and files that are not formatted make
+// the exit code 1
+pub fn main(args: &[String]) -> ExitCode {
+    let check = args.iter().any(|it| it == "--check");
+    let files = args
+        .iter()
+        .filter(|it| *it != "--check")
+        .collect::<Vec<_>>();
+
+    if files.is_empty() {
+        let mut input = String::new();
+        if let Err(error) = std::io::stdin().read_to_string(&mut input) {
+            eprintln!("<stdin>: {error}");
+            return ExitCode::from(2);
+        }
+        return match crisp::format(&input) {
+            Ok(output) if check && output != input => {
+                eprintln!("<stdin> is not formatted");
+                ExitCode::FAILURE
+            }
+            Ok(_) if check => ExitCode::SUCCESS,
+            Ok(output) => {
+                print!("{output}");
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                eprintln!("<stdin>: {error}");
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    for file in files {
+        let result = std::fs::read_to_string(file)
+            .map_err(crisp::Error::from)
+            .and_then(|input| Ok((crisp::format(&input)?, input)));
+        match result {
+            Ok((output, input)) if output == input => {}
+            Ok(_) if check => {
+                eprintln!("{file} is not formatted");
+                code = ExitCode::FAILURE;
+            }
+            Ok((output, _)) => {
+                if let Err(error) = std::fs::write(file, output) {
+                    eprintln!("{file}: {error}");
+                    return ExitCode::from(2);
+                }
+            }
+            Err(error) => {
+                eprintln!("{file}: {error}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    code
+}
@@ -0,0 +1,34 @@
+mod fmt;
+mod repl;
+mod run;
+
+use std::process::ExitCode;
+
+// Lets profiles count allocations
+#[global_allocator]
+static ALLOCATOR: crisp::CountingAllocator = crisp::CountingAllocator;
+
+const USAGE: &str = "\
+usage: crisp [command] [options]
+
+commands:
+    fmt [--check] [files...]    format files in place, or stdin to stdout
+    run <file> [args...]        evaluate a script, binding args to `args`
+    run --eval <code> [args...] evaluate code and print the result
+    run --profile <out> ...     also print a profile and write folded stacks
+
+without a command an interactive session is started";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        None => repl::main(),
+        Some("fmt") => fmt::main(&args[1..]),
+        Some("run") => run::main(&args[1..]),
+        Some("--eval") => run::main(&args),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
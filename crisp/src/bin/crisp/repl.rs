@@ -0,0 +1,201 @@
+use crisp::cst::{self, Element, Node, NodeKind, TokenKind};
+use crisp::{Backtrace, Capability, Context};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const PROMPT: &str = ">> ";
+const HELP: &str = "\
+:help     show this message
+:env      show every binding in the environment
+:reset    start over with an empty environment";
+const SPECIAL_FORMS: &[&str] = &["if", "let", "fn", "nil"];
+
+// Unclosed lists, strings and block comments continue on the next line
+fn incomplete(node: &Node) -> bool {
+    let closed = match node.kind {
+        NodeKind::List => {
+            matches!(node.children.last(), Some(Element::Token(it)) if it.kind == TokenKind::RightParen)
+        }
+        NodeKind::Quote | NodeKind::DatumComment => node.significant().count() > 1,
+        _ => true,
+    };
+    !closed
+        || node.children.iter().any(|child| match child {
+            Element::Node(node) => incomplete(node),
+            Element::Token(token) => {
+                token.kind == TokenKind::Error
+                    && (token.text.starts_with('"') || token.text.starts_with("#|"))
+            }
+        })
+}
+
+fn is_symbol(letter: char) -> bool {
+    letter.is_alphanumeric() || "_-<>?".contains(letter)
+}
+
+#[derive(Default)]
+struct Repl {
+    /// Everything that can be completed, refreshed after each evaluation
+    symbols: Vec<String>,
+}
+
+impl Completer for Repl {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, it)| is_symbol(*it))
+            .last()
+            .map(|(index, _)| index)
+            .unwrap_or(pos);
+        let prefix = &line[start..pos];
+        let candidates = self
+            .symbols
+            .iter()
+            .filter(|it| !prefix.is_empty() && it.starts_with(prefix))
+            .map(|it| Pair {
+                display: it.clone(),
+                replacement: it.clone(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Validator for Repl {
+    fn validate(&self, context: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(match incomplete(&cst::parse(context.input())) {
+            true => ValidationResult::Incomplete,
+            false => ValidationResult::Valid(None),
+        })
+    }
+}
+
+impl Hinter for Repl {
+    type Hint = String;
+}
+
+impl Highlighter for Repl {}
+
+impl Helper for Repl {}
+
+impl Repl {
+    fn refresh(&mut self, context: &Context) {
+        let symbols = context.symbols().chain(SPECIAL_FORMS.iter().copied());
+        self.symbols = symbols.map(String::from).collect();
+        self.symbols.sort();
+        self.symbols.dedup();
+    }
+}
+
+fn history() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".crisp_history"))
+}
+
+fn eval(input: &str, context: &mut Context) {
+    let exprs = match crisp::parse(input) {
+        Ok(exprs) => exprs,
+        Err(error) => return eprintln!("Parsing error: {error}"),
+    };
+    for expr in exprs {
+        match context.eval(expr) {
+            Ok(expr) => println!("{expr}"),
+            Err(error) => {
+                eprintln!("Evaluation error: {error}");
+                // Spans may point into earlier input, so only calls are shown
+                for frame in error
+                    .downcast_ref::<Backtrace>()
+                    .map_or(&[][..], |it| &it.frames)
+                {
+                    eprintln!("    in {frame}");
+                }
+            }
+        }
+    }
+}
+
+fn command(input: &str, context: &mut Context) {
+    match input {
+        ":help" => println!("{HELP}"),
+        ":env" => {
+            let mut bindings = context.bindings().collect::<Vec<_>>();
+            bindings.sort_by_key(|(name, _)| *name);
+            for (name, expr) in bindings {
+                println!("{name} = {expr}");
+            }
+        }
+        ":reset" => *context = trusted(),
+        _ => eprintln!("Unknown command {input}, try :help"),
+    }
+}
+
+// Code typed at the prompt can do anything the user can
+fn trusted() -> Context {
+    let mut context = Context::default();
+    for capability in Capability::ALL {
+        context.grant(*capability);
+    }
+    context
+}
+
+pub fn main() -> ExitCode {
+    let mut context = trusted();
+    let mut editor = Editor::<Repl>::new();
+    let mut repl = Repl::default();
+    repl.refresh(&context);
+    editor.set_helper(Some(repl));
+    let history = history();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let mut input = String::new();
+    loop {
+        match editor.readline(PROMPT) {
+            Ok(line) => {
+                // Piped input isn't validated, so gather lines until complete
+                input += &line;
+                if incomplete(&cst::parse(&input)) {
+                    input += "\n";
+                    continue;
+                }
+                let line = std::mem::take(&mut input);
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                editor.add_history_entry(line);
+                match line.starts_with(':') {
+                    true => command(line, &mut context),
+                    false => eval(line, &mut context),
+                }
+                if let Some(repl) = editor.helper_mut() {
+                    repl.refresh(&context);
+                }
+            }
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("{error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    ExitCode::SUCCESS
+}
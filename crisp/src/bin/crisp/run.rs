@@ -0,0 +1,98 @@
+use crisp::{Backtrace, Capability, Context, Expr};
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: crisp run [--profile <out>] <file> [args...] or crisp run [--profile <out>] --eval <code> [args...]";
+
+// Evaluate each form from the top, stopping at the first error. Returns the
+// value of the last form
+fn run(name: &str, input: &str, context: &mut Context) -> Option<Expr> {
+    let recovered = crisp::parse_recovering(input);
+    for error in &recovered.errors {
+        let (line, column) = error.span.location(input);
+        eprintln!("{name}:{line}:{column}: Parsing error: {}", error.message);
+    }
+    if !recovered.errors.is_empty() {
+        return None;
+    }
+
+    let mut value = Expr::Nil;
+    for form in recovered.forms {
+        if let crisp::Form::Expr(expr, span) = form {
+            match context.eval(expr) {
+                Ok(expr) => value = expr,
+                Err(error) => {
+                    let backtrace = error.downcast_ref::<Backtrace>();
+                    // Errors outside of any call are reported at the form
+                    let (line, column) = backtrace
+                        .and_then(|it| it.span)
+                        .unwrap_or(span)
+                        .location(input);
+                    eprintln!("{name}:{line}:{column}: Evaluation error: {error}");
+                    for frame in backtrace.map_or(&[][..], |it| &it.frames) {
+                        let (line, column) = frame.span.location(input);
+                        eprintln!("    in {frame} at {name}:{line}:{column}");
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+    Some(value)
+}
+
+// Command-line arguments after the script are bound to `args`
+pub fn main(args: &[String]) -> ExitCode {
+    // Profiles are printed to stderr, with folded stacks written to a file
+    let (profile, args) = match args {
+        [flag, path, rest @ ..] if flag == "--profile" => (Some(path), rest),
+        _ => (None, args),
+    };
+    let (name, input, rest) = match args {
+        [flag, code, rest @ ..] if flag == "--eval" => ("<eval>", code.clone(), rest),
+        [file, rest @ ..] if !file.starts_with('-') => match std::fs::read_to_string(file) {
+            Ok(input) => (file.as_str(), input, rest),
+            Err(error) => {
+                eprintln!("{file}: {error}");
+                return ExitCode::from(2);
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    // Scripts are run by the user, so they can do anything the user can
+    let mut context = Context::default();
+    for capability in Capability::ALL {
+        context.grant(*capability);
+    }
+    let strings = rest
+        .iter()
+        .map(|it| Expr::Constant(crisp::Atom::String(it.clone())))
+        .collect();
+    context.define("args", Expr::Quote(strings));
+
+    if profile.is_some() {
+        context.start_profiling();
+    }
+    let value = run(name, &input, &mut context);
+    if let (Some(path), Some(profile)) = (profile, context.stop_profiling()) {
+        eprint!("{}", profile.report());
+        if let Err(error) = std::fs::write(path, profile.folded()) {
+            eprintln!("{path}: {error}");
+            return ExitCode::from(2);
+        }
+    }
+
+    match value {
+        // One-liners print their result, scripts only what they print
+        Some(value) if name == "<eval>" => {
+            println!("{value}");
+            ExitCode::SUCCESS
+        }
+        Some(_) => ExitCode::SUCCESS,
+        None => ExitCode::FAILURE,
+    }
+}
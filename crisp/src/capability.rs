@@ -0,0 +1,60 @@
+use crate::*;
+use std::collections::HashSet;
+
+/// What evaluated code is allowed to do outside of the context. Contexts
+/// start without any, so untrusted code can be evaluated as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// read-file, list-dir and file-exists?
+    Read,
+    /// write-file, append-file, delete-file and make-dir
+    Write,
+    /// call-process, shell-command and start-process
+    Process,
+    /// env, which reads environment variables of the host
+    Env,
+}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[
+        Capability::Read,
+        Capability::Write,
+        Capability::Process,
+        Capability::Env,
+    ];
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::Read => write!(f, "read"),
+            Capability::Write => write!(f, "write"),
+            Capability::Process => write!(f, "process"),
+            Capability::Env => write!(f, "env"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Capabilities(HashSet<Capability>);
+
+impl Context {
+    pub fn grant(&mut self, capability: Capability) {
+        self.capabilities.0.insert(capability);
+    }
+
+    pub fn revoke(&mut self, capability: Capability) {
+        self.capabilities.0.remove(&capability);
+    }
+
+    pub fn is_granted(&self, capability: Capability) -> bool {
+        self.capabilities.0.contains(&capability)
+    }
+
+    #[throws]
+    pub(crate) fn require(&self, name: &str, capability: Capability) {
+        if !self.is_granted(capability) {
+            bail!("{name} needs the {capability} capability");
+        }
+    }
+}
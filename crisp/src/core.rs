@@ -1,22 +1,432 @@
+use crate::lazy::{self, Items};
 use crate::*;
+use num::ToPrimitive;
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
 
-// In-built functions for data types
-pub fn std(head: &Expr, tail: &[Expr]) -> Result<Expr, Error> {
-    match head {
-        Expr::Constant(Atom::Symbol(name)) => match (name.as_str(), tail) {
-            ("car", [Expr::Quote(items)]) => match items.len() {
-                0 => Ok(Expr::Nil),
-                _ => Ok(items[0].clone()),
-            },
-            ("car", [Expr::Nil]) => Ok(Expr::Nil),
-            ("cdr", [Expr::Quote(items)]) => match items.len() {
-                0 | 1 => Ok(Expr::Nil),
-                2 => Ok(items[1].clone()),
-                _ => Ok(Expr::Quote(items[1..].to_vec())),
-            },
-            ("cdr", [Expr::Nil]) => Ok(Expr::Nil),
-            _ => bail!("No valid functions found"),
-        },
-        _ => bail!("No valid functions found"),
+const ANY: usize = usize::MAX;
+
+/// In-built functions and how many arguments they take
+pub const FUNCTIONS: &[(&str, RangeInclusive<usize>)] = &[
+    ("car", 1..=1),
+    ("cdr", 1..=1),
+    ("env", 1..=1),
+    ("debug", 0..=0),
+    ("backtrace", 0..=0),
+    ("cell", 1..=1),
+    ("cell-get", 1..=1),
+    ("cell-set", 2..=2),
+    ("gc-stats", 0..=0),
+    ("coroutine", 1..=ANY),
+    ("resume", 1..=2),
+    ("done?", 1..=1),
+    ("yield", 0..=1),
+    ("generator", 1..=ANY),
+    ("doc", 1..=1),
+    ("arglist", 1..=1),
+    ("defined-at", 1..=1),
+    ("bound?", 1..=1),
+    ("mod", 2..=2),
+    ("pow", 2..=2),
+    ("gcd", 1..=ANY),
+    ("lcm", 1..=ANY),
+    ("bit-and", 1..=ANY),
+    ("bit-or", 1..=ANY),
+    ("bit-xor", 1..=ANY),
+    ("shift-left", 2..=2),
+    ("shift-right", 2..=2),
+    ("sqrt", 1..=1),
+    ("exp", 1..=1),
+    ("ln", 1..=1),
+    ("log", 1..=2),
+    ("sin", 1..=1),
+    ("cos", 1..=1),
+    ("tan", 1..=1),
+    ("asin", 1..=1),
+    ("acos", 1..=1),
+    ("atan", 1..=1),
+    ("atan2", 2..=2),
+    ("floor", 1..=1),
+    ("ceil", 1..=1),
+    ("round", 1..=1),
+    ("truncate", 1..=1),
+    ("float", 1..=1),
+    ("int", 1..=1),
+    ("format-float", 2..=2),
+    ("regex", 1..=1),
+    ("re-match", 2..=2),
+    ("re-find", 2..=2),
+    ("re-find-all", 2..=2),
+    ("re-replace", 3..=3),
+    ("re-split", 2..=2),
+    ("read-file", 1..=1),
+    ("write-file", 2..=2),
+    ("append-file", 2..=2),
+    ("list-dir", 1..=1),
+    ("file-exists?", 1..=1),
+    ("delete-file", 1..=1),
+    ("make-dir", 1..=1),
+    ("path-join", 1..=ANY),
+    ("path-parent", 1..=1),
+    ("path-name", 1..=1),
+    ("path-extension", 1..=1),
+    ("call-process", 2..=ANY),
+    ("shell-command", 1..=ANY),
+    ("start-process", 2..=ANY),
+    ("wait-process", 1..=1),
+    ("poll-processes", 0..=0),
+    ("json-parse", 1..=3),
+    ("json-encode", 1..=3),
+    ("format", 1..=ANY),
+    ("print", 1..=ANY),
+    ("println", 0..=ANY),
+    ("random", 1..=2),
+    ("random-float", 0..=2),
+    ("random-choice", 1..=1),
+    ("shuffle", 1..=1),
+    ("random-seed", 1..=1),
+    ("to-string", 1..=2),
+    ("parse-number", 1..=2),
+    ("list", 0..=ANY),
+    ("map", 2..=ANY),
+    ("filter", 2..=2),
+    ("reduce", 2..=3),
+    ("fold", 3..=3),
+    ("apply", 2..=ANY),
+    ("funcall", 1..=ANY),
+    ("any?", 2..=2),
+    ("every?", 2..=2),
+    ("take", 2..=2),
+    ("take-while", 2..=2),
+    ("drop", 2..=2),
+    ("zip", 1..=ANY),
+    ("iterate", 2..=2),
+    ("unfold", 2..=2),
+    ("range", 0..=3),
+    ("sort", 1..=1),
+    ("sort-by", 2..=2),
+    ("group-by", 2..=2),
+];
+
+pub(crate) fn arity(name: &str) -> Option<&'static RangeInclusive<usize>> {
+    FUNCTIONS
+        .iter()
+        .find(|(it, _)| *it == name)
+        .map(|(_, arity)| arity)
+}
+
+// Describe the arity of a function, cdr expects 1 parameter
+pub(crate) fn expects(name: &str, arity: &RangeInclusive<usize>) -> String {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    match (*arity.start(), *arity.end()) {
+        (start, end) if start == end => {
+            format!("{name} expects {start} parameter{}", plural(start))
+        }
+        (start, ANY) => format!("{name} expects at least {start} parameter{}", plural(start)),
+        (start, end) => format!("{name} expects {start} to {end} parameters"),
+    }
+}
+
+fn list(items: Vec<Expr>) -> Expr {
+    match items.is_empty() {
+        true => Expr::Nil,
+        false => Expr::Quote(List::from(items)),
+    }
+}
+
+// Every element of a sequence, which never returns for infinite ones
+#[throws]
+fn elements(context: &mut Context, expr: &Expr) -> Vec<Expr> {
+    Items::new(expr)?.collect(context)?
+}
+
+#[throws]
+fn sequences(exprs: &[Expr]) -> (Vec<Items>, bool) {
+    let items = exprs
+        .iter()
+        .map(Items::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    let lazy = items.iter().any(Items::is_lazy);
+    (items, lazy)
+}
+
+// Lazy if it was made from a lazy sequence, otherwise a list
+#[throws]
+fn lazy_or_list(context: &mut Context, lazy: bool, sequence: impl Sequence + 'static) -> Expr {
+    match lazy {
+        true => Expr::Lazy(Lazy::new(sequence)),
+        false => list(Items::Lazy(Lazy::new(sequence)).collect(context)?),
+    }
+}
+
+#[throws]
+fn count(expr: &Expr) -> usize {
+    match expr {
+        Expr::Constant(Atom::Number(number)) if let Some(count) = number.to_usize() => count,
+        _ => bail!("Expected a positive number, found {expr}"),
+    }
+}
+
+#[throws]
+fn compare(a: &Expr, b: &Expr) -> Ordering {
+    match (a, b) {
+        (Expr::Constant(Atom::Number(a)), Expr::Constant(Atom::Number(b))) => a.cmp(b),
+        (Expr::Constant(Atom::Float(a)), Expr::Constant(Atom::Float(b))) => {
+            a.partial_cmp(b).unwrap_or(Ordering::Equal)
+        }
+        (Expr::Constant(Atom::String(a)), Expr::Constant(Atom::String(b))) => a.cmp(b),
+        (Expr::Constant(Atom::Char(a)), Expr::Constant(Atom::Char(b))) => a.cmp(b),
+        (Expr::Constant(Atom::Keyword(a)), Expr::Constant(Atom::Keyword(b))) => a.cmp(b),
+        _ => bail!("Can't compare {a} with {b}"),
+    }
+}
+
+// Sort items by their keys, which all have to be comparable
+#[throws]
+fn sort(mut items: Vec<(Expr, Expr)>) -> Expr {
+    if let Some((first, _)) = items.first() {
+        for (key, _) in &items {
+            compare(first, key)?;
+        }
+    }
+    items.sort_by(|a, b| compare(&a.0, &b.0).unwrap_or(Ordering::Equal));
+    list(items.into_iter().map(|(_, item)| item).collect())
+}
+
+#[throws]
+fn range(tail: &[Expr]) -> Expr {
+    if tail.is_empty() {
+        return Expr::Lazy(Lazy::new(lazy::Count(BigInt::from(0))));
+    }
+    let numbers = tail
+        .iter()
+        .map(|it| match it {
+            Expr::Constant(Atom::Number(number)) => Ok(number.clone()),
+            _ => Err(anyhow::anyhow!("range expects numbers, found {it}")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let zero = BigInt::from(0);
+    let (mut start, end, step) = match numbers.as_slice() {
+        [end] => (zero.clone(), end.clone(), BigInt::from(1)),
+        [start, end] => (start.clone(), end.clone(), BigInt::from(1)),
+        [start, end, step] => (start.clone(), end.clone(), step.clone()),
+        _ => unreachable!(),
+    };
+    if step == zero {
+        bail!("range expects a step other than 0");
+    }
+    let mut items = Vec::new();
+    while (step > zero && start < end) || (step < zero && start > end) {
+        items.push(Expr::Constant(Atom::Number(start.clone())));
+        start += &step;
+    }
+    list(items)
+}
+
+// In-built functions, None if there is no function with that name
+pub fn std(context: &mut Context, head: &Expr, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let name = match head {
+        Expr::Constant(Atom::Symbol(name)) => name.as_str(),
+        _ => return Ok(None),
+    };
+    match arity(name) {
+        Some(arity) if !arity.contains(&tail.len()) => {
+            bail!("{}, got {}", expects(name, arity), tail.len())
+        }
+        Some(_) => {}
+        None => return Ok(None),
+    }
+    if let Some(output) = int::std(name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = math::std(name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = json::std(name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = output::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = random::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = pattern::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = fs::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = process::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = heap::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = coroutine::std(context, name, tail)? {
+        return Ok(Some(output));
+    }
+    if let Some(output) = doc::std(context, name, tail)? {
+        return Ok(Some(output));
     }
+    let output = match (name, tail) {
+        ("car", [items]) => Items::new(items)?.next(context)?.unwrap_or(Expr::Nil),
+        ("cdr", [Expr::Lazy(lazy)]) => Expr::Lazy(Lazy::new(lazy::Drop {
+            count: 1,
+            sequence: Items::Lazy(lazy.clone()),
+        })),
+        ("cdr", [items]) => {
+            let items = match items {
+                Expr::Quote(items) => items.clone(),
+                items => List::from(elements(context, items)?),
+            };
+            match items.len() {
+                0 | 1 => Expr::Nil,
+                2 => items[1].clone(),
+                _ => Expr::Quote(items.tail(1)),
+            }
+        }
+        ("env", [Expr::Constant(Atom::String(variable))]) => {
+            context.require(name, Capability::Env)?;
+            match std::env::var(variable) {
+                Ok(value) => Expr::Constant(Atom::String(value)),
+                Err(_) => Expr::Nil,
+            }
+        }
+        ("env", [it]) => bail!("env expects a string, found {it}"),
+        ("list", items) => list(items.to_vec()),
+        ("map", [function, rest @ ..]) => {
+            let (sequences, lazy) = sequences(rest)?;
+            let function = function.clone();
+            lazy_or_list(
+                context,
+                lazy,
+                lazy::Map {
+                    function,
+                    sequences,
+                },
+            )?
+        }
+        ("filter", [predicate, items]) => {
+            let (mut sequences, lazy) = sequences(std::slice::from_ref(items))?;
+            let filter = lazy::Filter {
+                predicate: predicate.clone(),
+                sequence: sequences.remove(0),
+            };
+            lazy_or_list(context, lazy, filter)?
+        }
+        ("reduce" | "fold", [function, rest @ .., items]) => {
+            let mut items = Items::new(items)?;
+            let first = match rest.first() {
+                Some(it) => Some(it.clone()),
+                None => items.next(context)?,
+            };
+            let mut accumulator = match first {
+                Some(it) => it,
+                // Like Common Lisp, so (reduce + '()) is (+)
+                None => return Ok(Some(context.call(function.clone(), Vec::new())?)),
+            };
+            while let Some(item) = items.next(context)? {
+                accumulator = context.call(function.clone(), vec![accumulator, item])?;
+            }
+            accumulator
+        }
+        ("apply", [function, arguments @ .., items]) => {
+            let items = elements(context, items)?;
+            let arguments = arguments.iter().cloned().chain(items).collect();
+            context.call(function.clone(), arguments)?
+        }
+        ("funcall", [function, arguments @ ..]) => {
+            context.call(function.clone(), arguments.to_vec())?
+        }
+        ("any?" | "every?", [predicate, items]) => {
+            let every = name == "every?";
+            let mut items = Items::new(items)?;
+            while let Some(item) = items.next(context)? {
+                let output = context.call(predicate.clone(), vec![item])?;
+                if eval::expr_to_boolean(&output)? != every {
+                    return Ok(Some(eval::boolean_to_expr(!every)));
+                }
+            }
+            eval::boolean_to_expr(every)
+        }
+        ("take", [n, items]) => {
+            let mut items = Items::new(items)?;
+            let mut taken = Vec::new();
+            for _ in 0..count(n)? {
+                match items.next(context)? {
+                    Some(item) => taken.push(item),
+                    None => break,
+                }
+            }
+            list(taken)
+        }
+        ("take-while", [predicate, items]) => {
+            let mut items = Items::new(items)?;
+            let mut taken = Vec::new();
+            while let Some(item) = items.next(context)? {
+                let output = context.call(predicate.clone(), vec![item.clone()])?;
+                if !eval::expr_to_boolean(&output)? {
+                    break;
+                }
+                taken.push(item);
+            }
+            list(taken)
+        }
+        ("drop", [n, items]) => {
+            let (mut sequences, lazy) = sequences(std::slice::from_ref(items))?;
+            let drop = lazy::Drop {
+                count: count(n)?,
+                sequence: sequences.remove(0),
+            };
+            lazy_or_list(context, lazy, drop)?
+        }
+        ("zip", rest) => {
+            let (sequences, lazy) = sequences(rest)?;
+            lazy_or_list(context, lazy, lazy::Zip(sequences))?
+        }
+        ("iterate", [function, value]) => Expr::Lazy(Lazy::new(lazy::Iterate {
+            function: function.clone(),
+            value: value.clone(),
+            started: false,
+        })),
+        ("unfold", [function, seed]) => Expr::Lazy(Lazy::new(lazy::Unfold {
+            function: function.clone(),
+            seed: Some(seed.clone()),
+        })),
+        ("range", tail) => range(tail)?,
+        ("sort", [items]) => sort(
+            elements(context, items)?
+                .into_iter()
+                .map(|it| (it.clone(), it))
+                .collect(),
+        )?,
+        ("sort-by", [key, items]) => {
+            let mut keyed = Vec::new();
+            for item in elements(context, items)? {
+                keyed.push((context.call(key.clone(), vec![item.clone()])?, item));
+            }
+            sort(keyed)?
+        }
+        // Groups in order of first appearance, as a list of (key (items...))
+        ("group-by", [key, items]) => {
+            let mut groups: Vec<(Expr, Vec<Expr>)> = Vec::new();
+            for item in elements(context, items)? {
+                let key = context.call(key.clone(), vec![item.clone()])?;
+                match groups.iter_mut().find(|(it, _)| *it == key) {
+                    Some((_, group)) => group.push(item),
+                    None => groups.push((key, vec![item])),
+                }
+            }
+            let groups = groups
+                .into_iter()
+                .map(|(key, items)| {
+                    Expr::Quote(List::from(vec![key, Expr::Quote(List::from(items))]))
+                })
+                .collect();
+            list(groups)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
 }
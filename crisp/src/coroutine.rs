@@ -0,0 +1,248 @@
+use crate::eval::{self, Applied};
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+// What is left to do in a coroutine, the top of the stack comes first
+#[derive(Clone, Serialize, Deserialize)]
+enum Task {
+    Eval(Expr),
+    /// Call a function with the arguments on top of the value stack
+    Apply(Expr, usize, Span),
+    Branch(Box<Expr>, Option<Box<Expr>>),
+    Define(Symbol, Option<String>, Span),
+    Push(Expr),
+    Yield,
+    /// Pop the frames of a call, counting from where the coroutine resumed
+    Return(usize),
+}
+
+fn is_named(head: &Expr, names: &[&str]) -> bool {
+    matches!(head, Expr::Constant(Atom::Symbol(name)) if names.contains(&name.as_str()))
+}
+
+pub(crate) enum Resumed {
+    Yield(Expr),
+    Return(Expr),
+}
+
+/// Function call that can stop at (yield value) and be resumed later. It is
+/// evaluated with its own stack of tasks instead of the Rust stack, so a
+/// suspended coroutine is just data that can be cloned, traced by the
+/// collector and saved in images
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Coroutine {
+    tasks: Vec<Task>,
+    values: Vec<Expr>,
+    suspended: bool,
+    #[serde(skip)]
+    frames: Vec<Frame>,
+}
+
+impl Coroutine {
+    pub(crate) fn new(context: &Context, function: Expr, arguments: Vec<Expr>) -> Self {
+        // Attributed to where the coroutine was made, like calls from in-built
+        // functions
+        let span = context.stack.last().map(|it| it.span).unwrap_or_default();
+        Self {
+            tasks: vec![Task::Apply(function, arguments.len(), span)],
+            values: arguments,
+            suspended: false,
+            frames: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Run until the next yield or until the function returns. The value is
+    /// what the yield it was suspended at returns
+    pub(crate) fn resume(&mut self, context: &mut Context, value: Expr) -> Result<Resumed, Error> {
+        if self.is_done() {
+            bail!("Coroutine has finished");
+        }
+        if self.suspended {
+            self.values.push(value);
+        }
+        let base = context.stack.len();
+        for frame in self.frames.drain(..) {
+            context.push_frame(frame);
+        }
+        let result = self
+            .run(context, base)
+            .map_err(|error| Backtrace::attach(error, &context.stack));
+        match result {
+            Ok(Resumed::Yield(_)) => {
+                self.suspended = true;
+                self.frames = context.stack[base..].to_vec();
+            }
+            // Errors finish the coroutine as well
+            _ => {
+                self.tasks.clear();
+                self.values.clear();
+            }
+        }
+        context.pop_frames(base);
+        result
+    }
+
+    fn pop(&mut self) -> Expr {
+        self.values.pop().unwrap_or(Expr::Nil)
+    }
+
+    fn run(&mut self, context: &mut Context, base: usize) -> Result<Resumed, Error> {
+        while let Some(task) = self.tasks.pop() {
+            match task {
+                Task::Eval(Expr::Call(head, mut tail, _)) if is_named(&head, &["yield"]) => {
+                    if let Some(arity) = core::arity("yield").filter(|it| !it.contains(&tail.len()))
+                    {
+                        bail!("{}, got {}", core::expects("yield", arity), tail.len());
+                    }
+                    self.tasks.push(Task::Yield);
+                    self.tasks.push(Task::Eval(tail.pop().unwrap_or(Expr::Nil)));
+                }
+                Task::Eval(Expr::Call(head, tail, span)) => {
+                    self.tasks.push(Task::Apply(*head, tail.len(), span));
+                    self.tasks.extend(tail.into_iter().rev().map(Task::Eval));
+                }
+                Task::Eval(Expr::If(predicate, then, otherwise)) => {
+                    self.tasks.push(Task::Branch(then, otherwise));
+                    self.tasks.push(Task::Eval(*predicate));
+                }
+                Task::Eval(Expr::Let(items, span)) => {
+                    self.tasks.push(Task::Push(Expr::Nil));
+                    for (name, value, doc) in items.into_iter().rev() {
+                        match name {
+                            Atom::Symbol(name) => self.tasks.push(Task::Define(name, doc, span)),
+                            _ => bail!("Expected symbol, found following: {name}"),
+                        }
+                        self.tasks.push(Task::Eval(*value));
+                    }
+                }
+                // Nothing else can yield
+                Task::Eval(expr) => {
+                    let value = context.eval(expr)?;
+                    self.values.push(value);
+                }
+                Task::Apply(head, count, span) => {
+                    let arguments = self.values.split_off(self.values.len() - count);
+                    // Forms that look at the stack, so they don't get a frame
+                    if is_named(&head, &["debug", "backtrace"]) {
+                        let value = context.eval(Expr::Call(Box::new(head), arguments, span))?;
+                        self.values.push(value);
+                        continue;
+                    }
+                    // Tail calls replace the frame of the call they came from
+                    match self.tasks.last() {
+                        Some(Task::Return(depth)) => context.pop_frames(base + depth),
+                        _ => self.tasks.push(Task::Return(context.stack.len() - base)),
+                    }
+                    context.push_frame(Frame {
+                        name: eval::frame_name(&head),
+                        span,
+                        arguments: arguments.clone(),
+                    });
+                    context.enter();
+                    match context.apply(head, arguments)? {
+                        Applied::Value(value) => self.values.push(value),
+                        Applied::Tail(body) => self.tasks.push(Task::Eval(body)),
+                    }
+                }
+                Task::Branch(then, otherwise) => {
+                    let predicate = self.pop();
+                    if eval::expr_to_boolean(&predicate)? {
+                        self.tasks.push(Task::Eval(*then));
+                    } else if let Some(branch) = otherwise {
+                        self.tasks.push(Task::Eval(*branch));
+                    } else {
+                        bail!("No branches of predicate ran: {predicate}")
+                    }
+                }
+                Task::Define(name, doc, span) => {
+                    let value = self.pop();
+                    context.bind(name, value, doc, span);
+                }
+                Task::Push(value) => self.values.push(value),
+                Task::Yield => return Ok(Resumed::Yield(self.pop())),
+                Task::Return(depth) => context.pop_frames(base + depth),
+            }
+        }
+        Ok(Resumed::Return(self.pop()))
+    }
+
+    pub(crate) fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        for task in &self.tasks {
+            match task {
+                Task::Eval(expr) | Task::Apply(expr, ..) | Task::Push(expr) => visit(expr),
+                Task::Branch(then, otherwise) => {
+                    visit(then);
+                    otherwise.iter().for_each(|it| visit(it));
+                }
+                Task::Define(..) | Task::Yield | Task::Return(_) => {}
+            }
+        }
+        self.values.iter().for_each(&mut *visit);
+        for frame in &self.frames {
+            frame.arguments.iter().for_each(&mut *visit);
+        }
+    }
+}
+
+// (generator f args...), the values f yields as a lazy sequence
+#[derive(Clone)]
+pub(crate) struct Generator(pub(crate) Coroutine);
+
+impl Sequence for Generator {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        if self.0.is_done() {
+            return Ok(None);
+        }
+        match self.0.resume(context, Expr::Nil)? {
+            Resumed::Yield(value) => Ok(Some(value)),
+            Resumed::Return(_) => Ok(None),
+        }
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        self.0.trace(visit);
+    }
+}
+
+impl Context {
+    // (resume coroutine value), coroutines live on the heap so every handle to
+    // one sees it move forward
+    pub(crate) fn resume(&mut self, cell: Cell, value: Expr) -> Result<Expr, Error> {
+        let mut coroutine = self.heap.take_coroutine(cell)?;
+        let result = coroutine.resume(self, value);
+        self.heap.put_coroutine(cell, coroutine);
+        match result? {
+            Resumed::Yield(value) | Resumed::Return(value) => Ok(value),
+        }
+    }
+}
+
+/// Coroutine functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("coroutine", [function, arguments @ ..]) => {
+            let coroutine = Coroutine::new(context, function.clone(), arguments.to_vec());
+            Expr::Coroutine(context.heap.allocate_coroutine(coroutine))
+        }
+        ("resume", [Expr::Coroutine(cell), value @ ..]) => {
+            let value = value.first().cloned().unwrap_or(Expr::Nil);
+            context.resume(*cell, value)?
+        }
+        ("done?", [Expr::Coroutine(cell)]) => eval::boolean_to_expr(context.heap.is_done(*cell)?),
+        ("resume" | "done?", [it, ..]) => bail!("{name} expects a coroutine, found {it}"),
+        // Coroutines handle yield themselves, so this is only reached from
+        // outside of one
+        ("yield", _) => bail!("yield can only be used inside a coroutine or generator"),
+        ("generator", [function, arguments @ ..]) => {
+            let coroutine = Coroutine::new(context, function.clone(), arguments.to_vec());
+            Expr::Lazy(Lazy::new(Generator(coroutine)))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
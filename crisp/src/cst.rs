@@ -0,0 +1,258 @@
+use crate::parse::{
+    block_comment, line_comment, parse_built_in, parse_char, parse_float, parse_keyword,
+    parse_number, parse_string, parse_symbol,
+};
+use crate::Span;
+use nom::{character::complete::multispace1, combinator::recognize, Parser};
+use nom_supreme::{error::ErrorTree, tag::complete::tag};
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenKind {
+    LeftParen,
+    RightParen,
+    /// '
+    Quote,
+    /// #_
+    DatumComment,
+    Whitespace,
+    LineComment,
+    BlockComment,
+    String,
+    Char,
+    Number,
+    Float,
+    Operator,
+    Keyword,
+    Symbol,
+    /// Text the reader does not understand
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace | Self::LineComment | Self::BlockComment
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NodeKind {
+    /// Every top-level form and the trivia between them
+    Root,
+    /// (head tail ...), the closing paren is missing when unterminated
+    List,
+    /// 'datum
+    Quote,
+    /// #_datum
+    DatumComment,
+    /// Stray closing paren
+    Error,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+impl Element {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Node(node) => node.span,
+            Self::Token(token) => token.span,
+        }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        match self {
+            Self::Node(node) => node.kind == NodeKind::DatumComment,
+            Self::Token(token) => token.kind.is_trivia(),
+        }
+    }
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Node(node) => write!(f, "{node}"),
+            Self::Token(token) => write!(f, "{}", token.text),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+    pub span: Span,
+}
+
+impl Node {
+    fn new(kind: NodeKind, children: Vec<Element>, start: usize) -> Self {
+        let end = children.last().map(|it| it.span().end).unwrap_or(start);
+        Self {
+            kind,
+            children,
+            span: Span::new(start, end),
+        }
+    }
+
+    /// Children that are neither whitespace nor comments, including parens
+    pub fn significant(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter(|it| !it.is_trivia())
+    }
+}
+
+// Writing a node back out gives the exact text it was parsed from
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            write!(f, "{child}")?;
+        }
+        Ok(())
+    }
+}
+
+// Lexer
+fn lex<'a, O>(
+    input: &'a str,
+    parser: impl Parser<&'a str, O, ErrorTree<&'a str>>,
+) -> Option<&'a str> {
+    recognize(parser)(input).ok().map(|(_, text)| text)
+}
+
+// Unterminated strings and block comments swallow the rest of the input
+fn unterminated<'a>(input: &'a str, open: &str) -> Option<&'a str> {
+    input.starts_with(open).then_some(input)
+}
+
+fn unknown(input: &str) -> Option<&str> {
+    let end = input
+        .char_indices()
+        .skip(1)
+        .find(|(_, it)| it.is_whitespace() || "()';\"".contains(*it))
+        .map(|(index, _)| index)
+        .unwrap_or(input.len());
+    (end > 0).then(|| &input[..end])
+}
+
+type Lexer = fn(&str) -> Option<&str>;
+
+fn lex_token(input: &str) -> Option<(TokenKind, &str)> {
+    let lexers: [(TokenKind, Lexer); 16] = [
+        (TokenKind::Whitespace, |it| lex(it, multispace1)),
+        (TokenKind::LineComment, |it| lex(it, line_comment)),
+        (TokenKind::BlockComment, |it| lex(it, block_comment)),
+        (TokenKind::Error, |it| unterminated(it, "#|")),
+        (TokenKind::DatumComment, |it| lex(it, tag("#_"))),
+        (TokenKind::LeftParen, |it| lex(it, tag("("))),
+        (TokenKind::RightParen, |it| lex(it, tag(")"))),
+        (TokenKind::String, |it| lex(it, parse_string)),
+        (TokenKind::Error, |it| unterminated(it, "\"")),
+        (TokenKind::Char, |it| lex(it, parse_char)),
+        (TokenKind::Quote, |it| lex(it, tag("'"))),
+        (TokenKind::Float, |it| lex(it, parse_float)),
+        (TokenKind::Number, |it| lex(it, parse_number)),
+        (TokenKind::Operator, |it| lex(it, parse_built_in)),
+        (TokenKind::Keyword, |it| lex(it, parse_keyword)),
+        (TokenKind::Symbol, |it| lex(it, parse_symbol)),
+    ];
+    lexers
+        .iter()
+        .find_map(|(kind, lexer)| lexer(input).map(|text| (*kind, text)))
+        .or_else(|| unknown(input).map(|text| (TokenKind::Error, text)))
+}
+
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    while let Some((kind, text)) = lex_token(&input[start..]) {
+        tokens.push(Token {
+            kind,
+            text: text.to_string(),
+            span: Span::new(start, start + text.len()),
+        });
+        start += text.len();
+    }
+    tokens
+}
+
+// Tree
+struct Builder {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Builder {
+    fn trivia(&mut self, children: &mut Vec<Element>) {
+        while let Some(token) = self.tokens.next_if(|it| it.kind.is_trivia()) {
+            children.push(Element::Token(token));
+        }
+    }
+
+    // Next datum along with any trivia in front of it, the trivia goes into
+    // the parent so that nodes start and end on significant tokens
+    fn element(&mut self) -> Option<Element> {
+        let token = self.tokens.next()?;
+        let start = token.span.start;
+        let kind = match token.kind {
+            TokenKind::LeftParen => NodeKind::List,
+            TokenKind::Quote => NodeKind::Quote,
+            TokenKind::DatumComment => NodeKind::DatumComment,
+            TokenKind::RightParen => NodeKind::Error,
+            _ => return Some(Element::Token(token)),
+        };
+        let mut children = vec![Element::Token(token)];
+        match kind {
+            NodeKind::List => loop {
+                self.trivia(&mut children);
+                match self.tokens.peek().map(|it| it.kind) {
+                    None => break,
+                    Some(TokenKind::RightParen) => {
+                        children.extend(self.tokens.next().map(Element::Token));
+                        break;
+                    }
+                    Some(_) => children.extend(self.element()),
+                }
+            },
+            NodeKind::Quote | NodeKind::DatumComment => {
+                self.trivia(&mut children);
+                if self.tokens.peek().map(|it| it.kind) != Some(TokenKind::RightParen) {
+                    children.extend(self.element());
+                }
+            }
+            _ => {}
+        }
+        Some(Element::Node(Node::new(kind, children, start)))
+    }
+}
+
+/// Parse into a lossless syntax tree, which never fails since broken input
+/// ends up in error tokens and nodes
+pub fn parse(input: &str) -> Node {
+    let mut builder = Builder {
+        tokens: tokenize(input).into_iter().peekable(),
+    };
+    let mut children = Vec::new();
+    loop {
+        builder.trivia(&mut children);
+        match builder.element() {
+            Some(element) => children.push(element),
+            None => break,
+        }
+    }
+    Node {
+        kind: NodeKind::Root,
+        children,
+        span: Span::new(0, input.len()),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::*;
+
+/// A call on the evaluation stack
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    /// Name the function was called by, or fn for anonymous functions
+    pub name: Symbol,
+    /// Where the call was written
+    pub span: Span,
+    /// Arguments after evaluation
+    pub arguments: Vec<Expr>,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}", self.name)?;
+        for expr in &self.arguments {
+            write!(f, " {expr}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Error from `Context::eval` together with the calls that led to it,
+/// innermost first. Reach it with `error.downcast_ref::<Backtrace>()`
+#[derive(Debug)]
+pub struct Backtrace {
+    pub frames: Vec<Frame>,
+    /// Innermost call the error was raised in, if it came from one
+    pub span: Option<Span>,
+    error: Error,
+}
+
+impl Backtrace {
+    // Errors keep the backtrace from where they were first raised
+    pub(crate) fn attach(error: Error, stack: &[Frame]) -> Error {
+        match error.is::<Backtrace>() {
+            true => error,
+            false => Error::new(Backtrace {
+                frames: stack.iter().rev().cloned().collect(),
+                span: None,
+                error,
+            }),
+        }
+    }
+
+    // Calls around the one the error was raised in leave its span alone
+    pub(crate) fn locate(error: Error, span: Span, stack: &[Frame]) -> Error {
+        let mut error = Self::attach(error, stack);
+        if let Some(backtrace) = error.downcast_mut::<Backtrace>() {
+            backtrace.span.get_or_insert(span);
+        }
+        error
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Backtrace {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Breakpoint {
+    /// Pause whenever a function with this name is called
+    Function(String),
+    /// Pause on calls written inside this part of the source
+    Span(Span),
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Step {
+    /// Run until the next breakpoint or (debug)
+    #[default]
+    Continue,
+    /// Pause at the next call
+    Into,
+    /// Pause at the next call that isn't nested in the current one
+    Over,
+    /// Pause at the next call after the current one returns
+    Out,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Pause {
+    Breakpoint,
+    Step,
+    /// Evaluation reached (debug)
+    Debug,
+}
+
+/// Decides how to continue whenever evaluation pauses, for instance by
+/// asking the user. The paused call is the last frame in `Context::stack`
+pub trait Debugger {
+    fn pause(&mut self, reason: Pause, context: &Context) -> Step;
+}
+
+#[derive(Default)]
+pub(crate) struct Debugging {
+    debugger: Option<Box<dyn Debugger>>,
+    breakpoints: Vec<Breakpoint>,
+    step: Step,
+    /// Depth of the stack when the current step was requested
+    depth: usize,
+}
+
+impl Context {
+    /// Attach a debugger, or detach it with `None`. Returns the previous one
+    pub fn set_debugger(
+        &mut self,
+        debugger: Option<Box<dyn Debugger>>,
+    ) -> Option<Box<dyn Debugger>> {
+        self.debugging.step = Step::Continue;
+        std::mem::replace(&mut self.debugging.debugger, debugger)
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.debugging.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) {
+        self.debugging.breakpoints.retain(|it| it != breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.debugging.breakpoints
+    }
+
+    /// Calls being evaluated, outermost first
+    pub fn stack(&self) -> &[Frame] {
+        &self.stack
+    }
+
+    // Value of (backtrace), a list of (name arguments...) innermost first
+    pub(crate) fn backtrace(&self) -> Expr {
+        let frame = |frame: &Frame| {
+            let name = Expr::Constant(Atom::Symbol(frame.name));
+            Expr::Quote([name].into_iter().chain(frame.arguments.clone()).collect())
+        };
+        Expr::Quote(self.stack.iter().rev().map(frame).collect())
+    }
+
+    pub(crate) fn pause(&mut self, reason: Pause) {
+        if let Some(mut debugger) = self.debugging.debugger.take() {
+            let step = debugger.pause(reason, self);
+            self.debugging.debugger = Some(debugger);
+            self.debugging.step = step;
+            self.debugging.depth = self.stack.len();
+        }
+    }
+
+    // Called whenever a frame has been pushed
+    pub(crate) fn enter(&mut self) {
+        let frame = match (&self.debugging.debugger, self.stack.last()) {
+            (Some(_), Some(frame)) => frame,
+            _ => return,
+        };
+        let depth = self.stack.len();
+        let breakpoint = self.debugging.breakpoints.iter().any(|it| match it {
+            Breakpoint::Function(name) => frame.name == name.as_str(),
+            Breakpoint::Span(span) => span.contains(frame.span.start),
+        });
+        let step = match self.debugging.step {
+            Step::Continue => false,
+            Step::Into => true,
+            Step::Over => depth <= self.debugging.depth,
+            Step::Out => depth < self.debugging.depth,
+        };
+        if breakpoint {
+            self.pause(Pause::Breakpoint);
+        } else if step {
+            self.pause(Pause::Step);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// Documentation and location of a binding, for things like describing a
+/// function or showing its parameters while typing
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Definition {
+    pub doc: Option<String>,
+    /// Span of the let that made the binding, None when the host made it
+    pub span: Option<Span>,
+}
+
+// Names are passed as 'name or "name", in-built functions evaluate to their
+// own name so they can be passed as is
+#[throws]
+pub(crate) fn name<'a>(function: &str, expr: &'a Expr) -> &'a str {
+    match expr {
+        Expr::Quote(items)
+            if items.len() == 1
+                && let Expr::Constant(Atom::Symbol(name)) = &items[0] =>
+        {
+            name.as_str()
+        }
+        Expr::Constant(Atom::Symbol(name)) => name.as_str(),
+        Expr::Constant(Atom::String(name)) => name,
+        _ => bail!("{function} expects a symbol or string, found {expr}"),
+    }
+}
+
+impl Context {
+    // Bindings made by let replace what was known about the old binding
+    pub(crate) fn bind(&mut self, name: Symbol, expr: Expr, doc: Option<String>, span: Span) {
+        self.environment.insert(name, expr);
+        self.definitions.insert(
+            name,
+            Definition {
+                doc,
+                span: Some(span),
+            },
+        );
+    }
+
+    /// Document a binding, which is how functions defined by the host or
+    /// in-built functions get their documentation
+    pub fn document(&mut self, name: impl AsRef<str>, doc: impl Into<String>) {
+        let definition = self
+            .definitions
+            .entry(Symbol::new(name.as_ref()))
+            .or_default();
+        definition.doc = Some(doc.into());
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.get(&Symbol::get(name)?)
+    }
+
+    /// Parameters of the function bound to name
+    pub fn arglist(&self, name: &str) -> Option<Vec<Symbol>> {
+        match self.environment.get(&Symbol::get(name)?)? {
+            Expr::Function(args, _) => Some(
+                args.iter()
+                    .filter_map(|it| match it {
+                        Expr::Constant(Atom::Symbol(symbol)) => Some(*symbol),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Documentation functions, None if there is no function with that name.
+/// Arity is checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("doc", [symbol]) => {
+            let symbol = self::name(name, symbol)?;
+            match context.definition(symbol).and_then(|it| it.doc.clone()) {
+                Some(doc) => Expr::Constant(Atom::String(doc)),
+                None => Expr::Nil,
+            }
+        }
+        ("arglist", [symbol]) => {
+            let symbol = self::name(name, symbol)?;
+            let args = context.arglist(symbol).unwrap_or_default();
+            match args.is_empty() {
+                true => Expr::Nil,
+                false => Expr::Quote(
+                    args.into_iter()
+                        .map(|it| Expr::Constant(Atom::Symbol(it)))
+                        .collect(),
+                ),
+            }
+        }
+        // Byte offsets into the source the binding was evaluated from
+        ("defined-at", [symbol]) => {
+            let symbol = self::name(name, symbol)?;
+            match context.definition(symbol).and_then(|it| it.span) {
+                Some(span) => Expr::Quote(List::from(vec![
+                    Expr::Constant(Atom::Keyword(String::from("start"))),
+                    Expr::Constant(Atom::Number(BigInt::from(span.start))),
+                    Expr::Constant(Atom::Keyword(String::from("end"))),
+                    Expr::Constant(Atom::Number(BigInt::from(span.end))),
+                ])),
+                None => Expr::Nil,
+            }
+        }
+        ("bound?", [symbol]) => {
+            let symbol = self::name(name, symbol)?;
+            let bound = context.is_bound(symbol) || core::arity(symbol).is_some();
+            eval::boolean_to_expr(bound)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
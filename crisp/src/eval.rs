@@ -1,5 +1,6 @@
 use crate::*;
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 // Eval helpers
 #[throws]
@@ -27,18 +28,35 @@ fn float_to_expr(float: f64) -> Expr {
 }
 
 #[throws]
-fn expr_to_boolean(expr: &Expr) -> bool {
+pub(crate) fn expr_to_boolean(expr: &Expr) -> bool {
     match expr {
         Expr::Nil => false,
-        Expr::Quote(items) if items.len() == 0 => false,
+        Expr::Quote(items) if items.is_empty() => false,
         _ => true,
     }
 }
 
-fn boolean_to_expr(boolean: bool) -> Expr {
+// These run on every call and comparison, so their names are only interned
+// once instead of taking the lock each time
+pub(crate) fn frame_name(head: &Expr) -> Symbol {
+    // Or is the last operator
+    const COUNT: usize = BuiltIn::Or as usize + 1;
+    static BUILT_INS: [OnceLock<Symbol>; COUNT] = [const { OnceLock::new() }; COUNT];
+    static FUNCTION: OnceLock<Symbol> = OnceLock::new();
+    match head {
+        Expr::Constant(Atom::Symbol(symbol)) => *symbol,
+        Expr::Constant(Atom::BuiltIn(built_in)) => {
+            *BUILT_INS[*built_in as usize].get_or_init(|| Symbol::new(&built_in.to_string()))
+        }
+        _ => *FUNCTION.get_or_init(|| Symbol::new("fn")),
+    }
+}
+
+pub(crate) fn boolean_to_expr(boolean: bool) -> Expr {
+    static TRUE: OnceLock<Symbol> = OnceLock::new();
     match boolean {
         false => Expr::Nil,
-        true => Expr::Constant(Atom::Symbol(String::from("T"))),
+        true => Expr::Constant(Atom::Symbol(*TRUE.get_or_init(|| Symbol::new("T")))),
     }
 }
 
@@ -71,7 +89,7 @@ fn car<T>(tail: &[T]) -> Option<&T> {
 }
 
 fn cdr<T>(tail: &[T]) -> Option<&[T]> {
-    match tail.len() > 0 {
+    match !tail.is_empty() {
         true => Some(&tail[1..]),
         false => None,
     }
@@ -91,10 +109,10 @@ fn curry(expr: Expr, left: &[Expr], right: &[Expr], marked: &mut [bool]) -> Expr
             }
         },
         // Handle other forms
-        Expr::Call(head, tail) => {
+        Expr::Call(head, tail, span) => {
             let head = Box::new(single(*head)?);
             let tail = tail.into_iter().map(single).collect::<Result<Vec<_>, _>>()?;
-            Expr::Call(head, tail)
+            Expr::Call(head, tail, span)
         },
         Expr::If(predicate, then, otherwise) => Expr::If(Box::new(single(*predicate)?), Box::new(single(*then)?), otherwise.and_then(|it| single(*it).ok().map(Box::new))),
         it => it,
@@ -116,27 +134,104 @@ macro_rules! logic {
 	};
 }
 
+pub(crate) enum Applied {
+    Value(Expr),
+    Tail(Expr),
+}
+
 // Context
 #[derive(Default)]
 pub struct Context {
-    environment: HashMap<String, Expr>,
+    pub(crate) environment: HashMap<Symbol, Expr>,
+    pub(crate) stack: Vec<Frame>,
+    /// Evaluations and calls that haven't returned yet. Arguments are
+    /// evaluated before their call gets a frame, so the stack can't tell
+    pub(crate) depth: usize,
+    pub(crate) debugging: debug::Debugging,
+    pub(crate) profiler: Option<profile::Profiler>,
+    pub(crate) heap: heap::Heap,
+    /// Values the host holds on to, see Context::pin
+    pub(crate) pinned: Vec<Expr>,
+    pub(crate) definitions: HashMap<Symbol, Definition>,
+    pub(crate) patterns: pattern::Patterns,
+    pub(crate) capabilities: capability::Capabilities,
+    pub(crate) files: fs::Files,
+    pub(crate) processes: process::Processes,
+    pub(crate) output: Output,
+    pub(crate) random: random::Random,
 }
 
 impl Context {
-    pub fn eval(&mut self, mut expr: Expr) -> Result<Expr, Error> {
+    pub fn define(&mut self, name: impl AsRef<str>, expr: Expr) {
+        self.environment.insert(Symbol::new(name.as_ref()), expr);
+    }
+
+    pub fn is_bound(&self, name: &str) -> bool {
+        Symbol::get(name).is_some_and(|it| self.environment.contains_key(&it))
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &Expr)> {
+        self.environment.iter().map(|(name, expr)| (name.as_str(), expr))
+    }
+
+    /// Names that can be evaluated, both bindings and in-built functions
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        let functions = core::FUNCTIONS.iter().map(|(name, _)| *name);
+        self.environment.keys().map(|it| it.as_str()).chain(functions)
+    }
+
+    pub(crate) fn push_frame(&mut self, frame: Frame) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(frame.name);
+        }
+        self.stack.push(frame);
+    }
+
+    pub(crate) fn pop_frames(&mut self, base: usize) {
+        while self.stack.len() > base {
+            self.stack.pop();
+            if let Some(profiler) = &mut self.profiler {
+                profiler.exit();
+            }
+        }
+    }
+
+    pub fn eval(&mut self, expr: Expr) -> Result<Expr, Error> {
+        // Calls made while evaluating expr are popped when it returns
+        let base = self.stack.len();
+        self.depth += 1;
+        let result = self
+            .eval_from(expr, base)
+            .map_err(|error| Backtrace::attach(error, &self.stack));
+        self.pop_frames(base);
+        self.depth -= 1;
+        // Nothing but the roots and the result can hold on to cells once the
+        // outermost evaluation is done. Inner ones return while their
+        // siblings are still held by the caller
+        if self.depth == 0 && self.heap.should_collect() {
+            self.collect_from_roots(result.as_ref().ok());
+        }
+        result
+    }
+
+    fn eval_from(&mut self, mut expr: Expr, base: usize) -> Result<Expr, Error> {
         loop {
             match expr {
                 Expr::Constant(Atom::Symbol(symbol)) => match self.environment.get(&symbol) {
                     Some(expr) => return Ok(expr.clone()),
+                    // In-built functions evaluate to themselves, so they can be passed around
+                    None if core::arity(symbol.as_str()).is_some() => {
+                        return Ok(Expr::Constant(Atom::Symbol(symbol)))
+                    }
                     None => bail!("Invalid variable or function: {symbol}"),
                 },
                 Expr::Constant(_) | Expr::Quote(_) => return Ok(expr),
-                Expr::Let(items) => {
+                Expr::Let(items, span) => {
                     for item in items {
                         match item.0 {
                             Atom::Symbol(name) => {
                                 let expr = self.eval(*item.1)?;
-                                self.environment.insert(name, expr);
+                                self.bind(name, expr, item.2, span);
                             }
                             _ => bail!("Expected symbol, found following: {}", item.0),
                         }
@@ -155,119 +250,196 @@ impl Context {
                         bail!("No branches of predicate ran: {predicate}")
                     }
                 }
-                Expr::Call(head, tail) => {
-                    let tail = tail
-                        .into_iter()
-                        .map(|it| self.eval(it))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    
-                    // Try in-built functions, otherwise proceed
-                    if let Ok(expr) = core::std(&head, &tail) {
-                        return Ok(expr);
-                    }
-                    let head = self.eval(*head)?;
-                    
-                    match head {
-                        Expr::Function(args, fexpr) => {
-                            if tail.len() > args.len() {
-                                bail!(
-                                    "Expected maximum {} arguments, got {}",
-                                    args.len(),
-                                    tail.len()
-                                )
-                            }
-                            let mut marked = (0..args.len()).map(|_| false).collect::<Vec<_>>();
-                            let body = curry(*fexpr, &args, &tail, &mut marked)?;
-                            let args = args
-                                .into_iter()
-                                .zip(marked.into_iter())
-                                .filter_map(|(it, marked)| if marked { None } else { Some(it) })
-                                .collect::<Vec<_>>();
-                            if args.len() == 0 {
-                                expr = body;
-                                continue;
-                            } else {
-                                return Ok(Expr::Function(args, Box::new(body)));
-                            }
-                        }
-                        Expr::Constant(Atom::BuiltIn(built_in)) => {
-                            return Ok(match built_in {
-                                BuiltIn::Greater => logic!(tail => a > b),
-                                BuiltIn::Less => logic!(tail => a < b),
-                                BuiltIn::GreaterEqual => logic!(tail => a >= b),
-                                BuiltIn::LessEqual => logic!(tail => a <= b),
-                                BuiltIn::Plus => {
-                                    if let Ok(floats) = floats(&tail) {
-                                        float_to_expr(floats.sum())
-                                    } else if let Ok(numbers) = numbers(&tail) {
-                                        number_to_expr(numbers.sum())
-                                    } else {
-                                        bail!("+ expects number(s) or float(s), found none")
-                                    }
-                                }
-                                BuiltIn::Minus => {
-                                    if let Some(Ok(car)) = car(&tail).map(expr_to_float) {
-                                        float_to_expr(
-                                            floats(cdr(&tail).unwrap_or_default())?
-                                                .fold(car, |a, b| a - b),
-                                        )
-                                    } else if let Some(Ok(car)) = car(&tail).map(expr_to_number) {
-                                        number_to_expr(
-                                            numbers(cdr(&tail).unwrap_or_default())?
-                                                .fold(car, |a, b| a - b),
-                                        )
-                                    } else {
-                                        bail!(
-                                            "- expects 1 or more number(s) or float(s), found none"
-                                        )
-                                    }
-                                }
-                                BuiltIn::Times => {
-                                    if let Ok(floats) = floats(&tail) {
-                                        float_to_expr(floats.product())
-                                    } else if let Ok(numbers) = numbers(&tail) {
-                                        number_to_expr(numbers.product())
-                                    } else {
-                                        bail!("* expects number(s) or float(s), found none")
-                                    }
-                                }
-                                BuiltIn::Equal => {
-                                    boolean_to_expr(tail.windows(2).all(|it| it[0] == it[1]))
-                                }
-                                BuiltIn::NotEqual => {
-                                    boolean_to_expr(tail.windows(2).all(|it| it[0] != it[1]))
-                                }
-                                BuiltIn::And => boolean_to_expr(booleans(&tail)?.all(|it| it)),
-                                BuiltIn::Or => boolean_to_expr(booleans(&tail)?.any(|it| it)),
-                                BuiltIn::Divide => {
-                                    if let Some(Ok(car)) = car(&tail).map(expr_to_float) {
-                                        float_to_expr(
-                                            floats(cdr(&tail).unwrap_or_default())?
-                                                .fold(car, |a, b| a / b),
-                                        )
-                                    } else if let Some(Ok(car)) = car(&tail).map(expr_to_number) {
-                                        number_to_expr(
-                                            numbers(cdr(&tail).unwrap_or_default())?
-                                                .fold(car, |a, b| a / b),
-                                        )
-                                    } else {
-                                        bail!(
-                                            "/ expects 1 or more number(s) or float(s), found none"
-                                        )
-                                    }
-                                }
-                                BuiltIn::Not => match (tail.len() == 1, car(&tail)) {
-                                    (true, Some(car)) => boolean_to_expr(!expr_to_boolean(car)?),
-                                    _ => bail!("! expects 1 parameter, got {}", tail.len()),
-                                },
-                            });
+                Expr::Call(head, tail, span) => {
+                    // Errors are reported at the innermost call they came from
+                    let applied = self
+                        .eval_call(*head, tail, span, base)
+                        .map_err(|error| Backtrace::locate(error, span, &self.stack))?;
+                    match applied {
+                        Applied::Value(value) => return Ok(value),
+                        Applied::Tail(body) => {
+                            expr = body;
+                            continue;
                         }
-                        // In-built functions on data types
-                        it => return Ok(it),
                     }
                 }
                 it => return Ok(it),
             }
         }
     }
+
+    // Evaluate the arguments of a call and apply its head to them
+    fn eval_call(
+        &mut self,
+        head: Expr,
+        tail: Vec<Expr>,
+        span: Span,
+        base: usize,
+    ) -> Result<Applied, Error> {
+        let tail = tail
+            .into_iter()
+            .map(|it| self.eval(it))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Forms that look at the stack, so they don't get a frame.
+        // Bindings shadow them like any other in-built function
+        match &head {
+            Expr::Constant(Atom::Symbol(name)) if !self.environment.contains_key(name) => {
+                match (name.as_str(), tail.len()) {
+                    ("debug", 0) => {
+                        self.pause(Pause::Debug);
+                        return Ok(Applied::Value(Expr::Nil));
+                    }
+                    ("backtrace", 0) => return Ok(Applied::Value(self.backtrace())),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        // Tail calls replace the frame of the call they came from
+        self.pop_frames(base);
+        self.push_frame(Frame {
+            name: frame_name(&head),
+            span,
+            arguments: tail.clone(),
+        });
+        self.enter();
+
+        self.apply(head, tail)
+    }
+
+    // Call a function with evaluated arguments. Functions that are fully
+    // applied give back their body, so evaluating it can be a tail call
+    pub(crate) fn apply(&mut self, head: Expr, tail: Vec<Expr>) -> Result<Applied, Error> {
+        // Bindings shadow in-built functions, which otherwise evaluate to
+        // their own name
+        let head = self.eval(head)?;
+        if let Some(expr) = core::std(self, &head, &tail)? {
+            return Ok(Applied::Value(expr));
+        }
+
+        match head {
+            Expr::Function(args, fexpr) => {
+                if tail.len() > args.len() {
+                    bail!(
+                        "Expected maximum {} arguments, got {}",
+                        args.len(),
+                        tail.len()
+                    )
+                }
+                let mut marked = (0..args.len()).map(|_| false).collect::<Vec<_>>();
+                let body = curry(Arc::unwrap_or_clone(fexpr), &args, &tail, &mut marked)?;
+                let args = args
+                    .into_iter()
+                    .zip(marked)
+                    .filter_map(|(it, marked)| if marked { None } else { Some(it) })
+                    .collect::<Vec<_>>();
+                if args.is_empty() {
+                    Ok(Applied::Tail(body))
+                } else {
+                    Ok(Applied::Value(Expr::Function(args, Arc::new(body))))
+                }
+            }
+            Expr::Constant(Atom::BuiltIn(built_in)) => {
+                Ok(Applied::Value(match built_in {
+                    BuiltIn::Greater => logic!(tail => a > b),
+                    BuiltIn::Less => logic!(tail => a < b),
+                    BuiltIn::GreaterEqual => logic!(tail => a >= b),
+                    BuiltIn::LessEqual => logic!(tail => a <= b),
+                    BuiltIn::Plus => {
+                        // Integers first, so adding up nothing is 0 rather than -0.0
+                        if let Ok(numbers) = numbers(&tail) {
+                            number_to_expr(numbers.sum())
+                        } else if let Ok(floats) = floats(&tail) {
+                            float_to_expr(floats.sum())
+                        } else {
+                            bail!("+ expects number(s) or float(s), found none")
+                        }
+                    }
+                    BuiltIn::Minus => {
+                        if let Some(Ok(car)) = car(&tail).map(expr_to_float) {
+                            float_to_expr(
+                                floats(cdr(&tail).unwrap_or_default())?
+                                    .fold(car, |a, b| a - b),
+                            )
+                        } else if let Some(Ok(car)) = car(&tail).map(expr_to_number) {
+                            number_to_expr(
+                                numbers(cdr(&tail).unwrap_or_default())?
+                                    .fold(car, |a, b| a - b),
+                            )
+                        } else {
+                            bail!(
+                                "- expects 1 or more number(s) or float(s), found none"
+                            )
+                        }
+                    }
+                    BuiltIn::Times => {
+                        if let Ok(numbers) = numbers(&tail) {
+                            number_to_expr(numbers.product())
+                        } else if let Ok(floats) = floats(&tail) {
+                            float_to_expr(floats.product())
+                        } else {
+                            bail!("* expects number(s) or float(s), found none")
+                        }
+                    }
+                    BuiltIn::Equal => {
+                        boolean_to_expr(tail.windows(2).all(|it| it[0] == it[1]))
+                    }
+                    BuiltIn::NotEqual => {
+                        boolean_to_expr(tail.windows(2).all(|it| it[0] != it[1]))
+                    }
+                    BuiltIn::And => boolean_to_expr(booleans(&tail)?.all(|it| it)),
+                    BuiltIn::Or => boolean_to_expr(booleans(&tail)?.any(|it| it)),
+                    BuiltIn::Divide => {
+                        if let Some(Ok(car)) = car(&tail).map(expr_to_float) {
+                            float_to_expr(
+                                floats(cdr(&tail).unwrap_or_default())?
+                                    .fold(car, |a, b| a / b),
+                            )
+                        } else if let Some(Ok(car)) = car(&tail).map(expr_to_number) {
+                            number_to_expr(
+                                numbers(cdr(&tail).unwrap_or_default())?
+                                    .fold(car, |a, b| a / b),
+                            )
+                        } else {
+                            bail!(
+                                "/ expects 1 or more number(s) or float(s), found none"
+                            )
+                        }
+                    }
+                    BuiltIn::Not => match (tail.len() == 1, car(&tail)) {
+                        (true, Some(car)) => boolean_to_expr(!expr_to_boolean(car)?),
+                        _ => bail!("! expects 1 parameter, got {}", tail.len()),
+                    },
+                }))
+            }
+            // In-built functions on data types
+            it => Ok(Applied::Value(it)),
+        }
+    }
+
+    /// Call a function value, like `+` or one made with fn, with evaluated
+    /// arguments
+    pub(crate) fn call(&mut self, function: Expr, arguments: Vec<Expr>) -> Result<Expr, Error> {
+        let base = self.stack.len();
+        // Calls made by in-built functions are attributed to their call site
+        self.push_frame(Frame {
+            name: frame_name(&function),
+            span: self.stack.last().map(|it| it.span).unwrap_or_default(),
+            arguments: arguments.clone(),
+        });
+        self.enter();
+        self.depth += 1;
+        let result = self
+            .apply(function, arguments)
+            .and_then(|applied| match applied {
+                Applied::Value(value) => Ok(value),
+                Applied::Tail(body) => self.eval_from(body, base),
+            })
+            .map_err(|error| Backtrace::attach(error, &self.stack));
+        self.pop_frames(base);
+        self.depth -= 1;
+        result
+    }
 }
@@ -0,0 +1,196 @@
+use crate::cst::{self, Element, Node, NodeKind, TokenKind};
+use crate::*;
+
+const WIDTH: usize = 80;
+const INDENT: usize = 2;
+
+// Forms whose body is indented instead of aligned, with how many arguments
+// stay on the first line, (let (triple x) ...), (fn (x) ...), (if test ...)
+const BODY_FORMS: &[(&str, usize)] = &[("let", 1), ("fn", 1), ("if", 1)];
+
+enum Item<'a> {
+    Datum(&'a Element),
+    /// Line comment, and whether it is on the same line as what came before
+    Comment(&'a str, bool),
+}
+
+// Significant children and line comments, whitespace is thrown away. Blank
+// lines are recorded by index so top-level forms can keep their grouping
+fn items(node: &Node) -> (Vec<Item<'_>>, Vec<usize>) {
+    let mut items = Vec::new();
+    let mut blank = Vec::new();
+    let mut newline = true;
+    for child in &node.children {
+        match child {
+            Element::Token(token) if token.kind == TokenKind::Whitespace => {
+                let lines = token.text.matches('\n').count();
+                newline |= lines > 0;
+                if lines > 1 && !items.is_empty() {
+                    blank.push(items.len());
+                }
+            }
+            Element::Token(token) if token.kind == TokenKind::LineComment => {
+                items.push(Item::Comment(
+                    token.text.trim_end(),
+                    !newline && !items.is_empty(),
+                ));
+                newline = true;
+            }
+            Element::Token(token)
+                if matches!(token.kind, TokenKind::LeftParen | TokenKind::RightParen) => {}
+            element => {
+                items.push(Item::Datum(element));
+                newline = false;
+            }
+        }
+    }
+    (items, blank)
+}
+
+fn column(output: &str, start: usize) -> usize {
+    match output.rfind('\n') {
+        Some(index) => output[index + 1..].chars().count(),
+        None => start + output.chars().count(),
+    }
+}
+
+// Single line version of an element, if it has no line comments or newlines
+fn flat(element: &Element) -> Option<String> {
+    match element {
+        Element::Token(token) if token.text.contains('\n') => None,
+        Element::Token(token) => Some(token.text.clone()),
+        Element::Node(node) => {
+            let (items, _) = items(node);
+            let mut parts = Vec::new();
+            for item in items {
+                match item {
+                    Item::Datum(element) => parts.push(flat(element)?),
+                    Item::Comment(..) => return None,
+                }
+            }
+            Some(match node.kind {
+                NodeKind::List => format!("({})", parts.join(" ")),
+                _ => parts.concat(),
+            })
+        }
+    }
+}
+
+fn render(element: &Element, start: usize, quoted: bool) -> String {
+    let node = match element {
+        Element::Token(token) => return token.text.clone(),
+        Element::Node(node) => node,
+    };
+    if let Some(line) = flat(element).filter(|it| start + it.chars().count() <= WIDTH) {
+        return line;
+    }
+    let (items, _) = items(node);
+    match node.kind {
+        NodeKind::List => render_list(&items, start, quoted),
+        kind => {
+            let mut output = String::new();
+            for item in items {
+                match item {
+                    Item::Datum(element) => {
+                        let quoted = kind == NodeKind::Quote || quoted;
+                        output += &render(element, column(&output, start), quoted);
+                    }
+                    Item::Comment(comment, _) => {
+                        output += comment;
+                        output += "\n";
+                        output += &" ".repeat(start);
+                    }
+                }
+            }
+            output
+        }
+    }
+}
+
+fn render_list(items: &[Item], start: usize, quoted: bool) -> String {
+    let head = match items.first() {
+        Some(Item::Datum(Element::Token(token))) if !quoted => Some(token),
+        _ => None,
+    };
+    let body = head.and_then(|head| {
+        BODY_FORMS
+            .iter()
+            .find(|(name, _)| head.kind == TokenKind::Symbol && *name == head.text)
+    });
+    // Body forms indent by two, calls align arguments with the first one,
+    // everything else lines up with the first element
+    let (keep, indent) = match (head, body) {
+        (Some(_), Some((_, keep))) => (*keep, start + INDENT),
+        (Some(head), None) => (1, start + 2 + head.text.chars().count()),
+        (None, _) => (0, start + 1),
+    };
+    let mut output = String::from("(");
+    let mut newline = false;
+    let mut datums = 0;
+    for item in items {
+        match item {
+            Item::Datum(element) => {
+                // Quoted lists are data, so they are filled up to the width
+                let fits = || {
+                    let width = flat(element).map(|it| it.chars().count());
+                    width.map(|it| column(&output, start) + 1 + it <= WIDTH) == Some(true)
+                };
+                if newline || datums > keep && !(quoted && fits()) {
+                    output += "\n";
+                    output += &" ".repeat(indent);
+                } else if datums > 0 {
+                    output += " ";
+                }
+                output += &render(element, column(&output, start), quoted);
+                datums += 1;
+                newline = false;
+            }
+            Item::Comment(comment, trailing) => {
+                if *trailing {
+                    output += " ";
+                } else if output.len() > 1 {
+                    output += "\n";
+                    output += &" ".repeat(indent);
+                }
+                output += comment;
+                newline = true;
+            }
+        }
+    }
+    if newline {
+        output += "\n";
+        output += &" ".repeat(indent);
+    }
+    output + ")"
+}
+
+/// Format crisp source, keeping comments and the original spelling of
+/// literals. Formatting already formatted code gives the same code back
+#[throws]
+pub fn format(input: &str) -> String {
+    if let Some(error) = parse_recovering(input).errors.first() {
+        let (line, column) = error.span.location(input);
+        bail!("Syntax error at {line}:{column}: {}", error.message);
+    }
+    let root = cst::parse(input);
+    let (items, blank) = items(&root);
+    let mut output = String::new();
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            let trailing = matches!(item, Item::Comment(_, true));
+            output += match (trailing, blank.contains(&index)) {
+                (true, _) => " ",
+                (false, true) => "\n\n",
+                (false, false) => "\n",
+            };
+        }
+        match item {
+            Item::Datum(element) => output += &render(element, column(&output, 0), false),
+            Item::Comment(comment, _) => output += comment,
+        }
+    }
+    match output.is_empty() {
+        true => output,
+        false => output + "\n",
+    }
+}
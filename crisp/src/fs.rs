@@ -0,0 +1,246 @@
+use crate::eval::boolean_to_expr;
+use crate::*;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Where files are read from and written to. The wasm build has no disk, so
+/// it keeps files in memory, where None is a directory
+pub(crate) enum Files {
+    Disk,
+    Memory(BTreeMap<PathBuf, Option<String>>),
+}
+
+impl Default for Files {
+    fn default() -> Self {
+        match cfg!(target_arch = "wasm32") {
+            true => Files::Memory(BTreeMap::new()),
+            false => Files::Disk,
+        }
+    }
+}
+
+// Paths in memory are relative to the root, without . and .. The root is
+// the empty path, which is a directory that isn't stored
+fn normalize(path: &str) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(it) => normal.push(it),
+            Component::ParentDir => {
+                normal.pop();
+            }
+            _ => {}
+        }
+    }
+    normal
+}
+
+// Errors from the disk don't say which file they're about
+fn disk<T>(path: &str, result: std::io::Result<T>) -> Result<T, Error> {
+    result.map_err(|error| anyhow::anyhow!("{path}: {error}"))
+}
+
+impl Files {
+    #[throws]
+    fn read(&self, path: &str) -> String {
+        match self {
+            Files::Disk => disk(path, std::fs::read_to_string(path))?,
+            Files::Memory(files) => match files.get(&normalize(path)) {
+                Some(Some(text)) => text.clone(),
+                Some(None) => bail!("{path} is a directory"),
+                None if normalize(path) == Path::new("") => bail!("{path} is a directory"),
+                None => bail!("{path} doesn't exist"),
+            },
+        }
+    }
+
+    #[throws]
+    fn write(&mut self, path: &str, text: &str, append: bool) {
+        match self {
+            Files::Disk if append => {
+                use std::io::Write;
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path);
+                disk(path, file.and_then(|mut it| it.write_all(text.as_bytes())))?
+            }
+            Files::Disk => disk(path, std::fs::write(path, text))?,
+            Files::Memory(files) => {
+                let normal = normalize(path);
+                if normal == Path::new("") {
+                    bail!("{path} is a directory");
+                }
+                match normal.parent() {
+                    Some(parent) if parent != Path::new("") && files.get(parent) != Some(&None) => {
+                        bail!("{} isn't a directory", parent.display())
+                    }
+                    _ => {}
+                }
+                match files.entry(normal).or_insert_with(|| Some(String::new())) {
+                    Some(file) if append => file.push_str(text),
+                    Some(file) => *file = text.to_string(),
+                    None => bail!("{path} is a directory"),
+                }
+            }
+        }
+    }
+
+    // Names of the entries in a directory, sorted
+    #[throws]
+    fn list(&self, path: &str) -> Vec<String> {
+        let mut names: Vec<String> = match self {
+            Files::Disk => disk(
+                path,
+                std::fs::read_dir(path).and_then(|entries| {
+                    entries
+                        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                        .collect()
+                }),
+            )?,
+            Files::Memory(files) => {
+                let normal = normalize(path);
+                if normal != Path::new("") && files.get(&normal) != Some(&None) {
+                    bail!("{path} isn't a directory");
+                }
+                files
+                    .keys()
+                    .filter(|it| it.parent() == Some(normal.as_path()))
+                    .filter_map(|it| it.file_name())
+                    .map(|it| it.to_string_lossy().into_owned())
+                    .collect()
+            }
+        };
+        names.sort();
+        names
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        match self {
+            Files::Disk => Path::new(path).exists(),
+            Files::Memory(files) => {
+                let normal = normalize(path);
+                normal == Path::new("") || files.contains_key(&normal)
+            }
+        }
+    }
+
+    #[throws]
+    fn delete(&mut self, path: &str) {
+        match self {
+            Files::Disk => disk(path, std::fs::remove_file(path))?,
+            Files::Memory(files) => match files.get(&normalize(path)) {
+                Some(Some(_)) => {
+                    files.remove(&normalize(path));
+                }
+                Some(None) => bail!("{path} is a directory"),
+                None if normalize(path) == Path::new("") => bail!("{path} is a directory"),
+                None => bail!("{path} doesn't exist"),
+            },
+        }
+    }
+
+    // Parents are made as well
+    #[throws]
+    fn make_dir(&mut self, path: &str) {
+        match self {
+            Files::Disk => disk(path, std::fs::create_dir_all(path))?,
+            Files::Memory(files) => {
+                let mut directory = PathBuf::new();
+                for component in normalize(path).components() {
+                    directory.push(component);
+                    if let Some(Some(_)) = files.insert(directory.clone(), None) {
+                        bail!("{} is a file", directory.display())
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Context {
+    /// Keep files in memory instead of on disk, like the wasm build does.
+    /// Files written before are forgotten
+    pub fn use_memory_fs(&mut self) {
+        self.files = Files::Memory(BTreeMap::new());
+    }
+}
+
+#[throws]
+fn text<'a>(name: &str, expr: &'a Expr) -> &'a str {
+    match expr {
+        Expr::Constant(Atom::String(text)) => text.as_str(),
+        _ => bail!("{name} expects a string, found {expr}"),
+    }
+}
+
+fn string(string: impl Into<String>) -> Expr {
+    Expr::Constant(Atom::String(string.into()))
+}
+
+fn optional(string: Option<&std::ffi::OsStr>) -> Expr {
+    match string {
+        Some(string) => self::string(string.to_string_lossy()),
+        None => Expr::Nil,
+    }
+}
+
+/// File functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("read-file", [path]) => {
+            context.require(name, Capability::Read)?;
+            string(context.files.read(text(name, path)?)?)
+        }
+        ("write-file" | "append-file", [path, contents]) => {
+            context.require(name, Capability::Write)?;
+            let append = name == "append-file";
+            context
+                .files
+                .write(text(name, path)?, text(name, contents)?, append)?;
+            Expr::Nil
+        }
+        ("list-dir", [path]) => {
+            context.require(name, Capability::Read)?;
+            let names = context.files.list(text(name, path)?)?;
+            match names.is_empty() {
+                true => Expr::Nil,
+                false => Expr::Quote(names.into_iter().map(string).collect()),
+            }
+        }
+        ("file-exists?", [path]) => {
+            context.require(name, Capability::Read)?;
+            boolean_to_expr(context.files.exists(text(name, path)?))
+        }
+        ("delete-file", [path]) => {
+            context.require(name, Capability::Write)?;
+            context.files.delete(text(name, path)?)?;
+            Expr::Nil
+        }
+        ("make-dir", [path]) => {
+            context.require(name, Capability::Write)?;
+            context.files.make_dir(text(name, path)?)?;
+            Expr::Nil
+        }
+        // Paths are only strings, so these don't need a capability
+        ("path-join", [first, rest @ ..]) => {
+            let mut path = PathBuf::from(text(name, first)?);
+            for part in rest {
+                path.push(text(name, part)?);
+            }
+            string(path.to_string_lossy())
+        }
+        // (path-parent "a") is nil rather than ""
+        ("path-parent", [path]) => optional(
+            Path::new(text(name, path)?)
+                .parent()
+                .filter(|it| *it != Path::new(""))
+                .map(|it| it.as_os_str()),
+        ),
+        ("path-name", [path]) => optional(Path::new(text(name, path)?).file_name()),
+        ("path-extension", [path]) => optional(Path::new(text(name, path)?).extension()),
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,312 @@
+use crate::coroutine::Coroutine;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+// Collections run once this many cells have been made since the last one,
+// or as many as survived it if that is more
+const THRESHOLD: usize = 1024;
+
+/// Handle to a mutable cell on the heap of a Context, made with (cell value),
+/// or to a coroutine. Handles to collected cells are detected, since slots
+/// are reused with a new generation
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Cell {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct HeapStats {
+    /// Cells that survived the last collection, or were made since
+    pub live: usize,
+    /// Slots the heap has room for without growing
+    pub capacity: usize,
+    pub collections: usize,
+    /// Cells freed over every collection
+    pub collected: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Object {
+    Value(Expr),
+    /// Taken out while it is running
+    Coroutine(Option<Box<Coroutine>>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    value: Option<Object>,
+}
+
+/// Cells are traced from the environment, so cycles between cells and the
+/// values they hold are collected like anything else
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub(crate) struct Heap {
+    slots: Vec<Slot>,
+    #[serde(skip)]
+    free: Vec<usize>,
+    #[serde(skip)]
+    allocated: usize,
+    #[serde(skip)]
+    survivors: usize,
+    #[serde(skip)]
+    collections: usize,
+    #[serde(skip)]
+    collected: usize,
+}
+
+impl Heap {
+    // Free slots aren't stored in images, so find them again after loading
+    pub(crate) fn restore(&mut self) {
+        self.free = (0..self.slots.len())
+            .filter(|index| self.slots[*index].value.is_none())
+            .collect();
+    }
+
+    // Cells that can't be written to an image are left empty, so they read
+    // as collected after loading
+    pub(crate) fn savable(&self) -> Heap {
+        let mut heap = self.clone();
+        for slot in &mut heap.slots {
+            let savable = match &slot.value {
+                Some(Object::Value(value)) => image::is_savable(value),
+                Some(Object::Coroutine(Some(coroutine))) => {
+                    let mut savable = true;
+                    coroutine.trace(&mut |it| savable &= image::is_savable(it));
+                    savable
+                }
+                _ => true,
+            };
+            if !savable {
+                slot.value = None;
+            }
+        }
+        heap
+    }
+
+    pub(crate) fn allocate(&mut self, value: Expr) -> Cell {
+        self.insert(Object::Value(value))
+    }
+
+    pub(crate) fn allocate_coroutine(&mut self, coroutine: Coroutine) -> Cell {
+        self.insert(Object::Coroutine(Some(Box::new(coroutine))))
+    }
+
+    fn insert(&mut self, value: Object) -> Cell {
+        self.allocated += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                Cell {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                Cell {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    #[throws]
+    fn slot(&mut self, cell: Cell) -> &mut Object {
+        match self.slots.get_mut(cell.index) {
+            Some(Slot {
+                generation,
+                value: Some(value),
+            }) if *generation == cell.generation => value,
+            _ => bail!("Cell was collected"),
+        }
+    }
+
+    #[throws]
+    pub(crate) fn get(&mut self, cell: Cell) -> Expr {
+        match self.slot(cell)? {
+            Object::Value(value) => value.clone(),
+            Object::Coroutine(_) => bail!("Expected a cell, found <coroutine>"),
+        }
+    }
+
+    #[throws]
+    pub(crate) fn set(&mut self, cell: Cell, value: Expr) {
+        *self.slot(cell)? = Object::Value(value);
+    }
+
+    // Coroutines are taken out of the heap while they run, so they can't
+    // resume themselves
+    #[throws]
+    pub(crate) fn take_coroutine(&mut self, cell: Cell) -> Box<Coroutine> {
+        match self.slot(cell)? {
+            Object::Coroutine(coroutine) => match coroutine.take() {
+                Some(coroutine) => coroutine,
+                None => bail!("Coroutine is already running"),
+            },
+            Object::Value(_) => bail!("Expected a coroutine, found <cell>"),
+        }
+    }
+
+    pub(crate) fn put_coroutine(&mut self, cell: Cell, coroutine: Box<Coroutine>) {
+        if let Ok(Object::Coroutine(slot)) = self.slot(cell) {
+            *slot = Some(coroutine);
+        }
+    }
+
+    #[throws]
+    pub(crate) fn is_done(&mut self, cell: Cell) -> bool {
+        match self.slot(cell)? {
+            Object::Coroutine(Some(coroutine)) => coroutine.is_done(),
+            Object::Coroutine(None) => false,
+            Object::Value(_) => bail!("Expected a coroutine, found <cell>"),
+        }
+    }
+
+    pub(crate) fn live(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub(crate) fn should_collect(&self) -> bool {
+        self.allocated >= THRESHOLD.max(self.survivors)
+    }
+
+    fn mark(&self, expr: &Expr, marks: &mut [bool]) {
+        match expr {
+            Expr::Cell(cell) | Expr::Coroutine(cell) => {
+                if let Some(slot) = self.slots.get(cell.index) {
+                    if slot.generation == cell.generation && !marks[cell.index] {
+                        marks[cell.index] = true;
+                        match &slot.value {
+                            Some(Object::Value(value)) => self.mark(value, marks),
+                            Some(Object::Coroutine(Some(coroutine))) => {
+                                coroutine.trace(&mut |it| self.mark(it, marks))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Expr::Call(head, tail, _) => {
+                self.mark(head, marks);
+                tail.iter().for_each(|it| self.mark(it, marks));
+            }
+            Expr::If(predicate, then, otherwise) => {
+                self.mark(predicate, marks);
+                self.mark(then, marks);
+                otherwise.iter().for_each(|it| self.mark(it, marks));
+            }
+            Expr::Quote(items) => items.iter().for_each(|it| self.mark(it, marks)),
+            Expr::Let(items, _) => items.iter().for_each(|it| self.mark(&it.1, marks)),
+            Expr::Function(_, body) => self.mark(body, marks),
+            Expr::Lazy(lazy) => lazy.trace(&mut |it| self.mark(it, marks)),
+            Expr::Constant(_) | Expr::Regex(_) | Expr::Nil => {}
+        }
+    }
+
+    /// Free every cell that can't be reached from the roots, returning how
+    /// many were freed
+    pub(crate) fn collect<'a>(&mut self, roots: impl Iterator<Item = &'a Expr>) -> usize {
+        let mut marks = vec![false; self.slots.len()];
+        for root in roots {
+            self.mark(root, &mut marks);
+        }
+        let mut collected = 0;
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if !marks[index] && slot.value.is_some() {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(index);
+                collected += 1;
+            }
+        }
+        self.allocated = 0;
+        self.survivors = self.live();
+        self.collections += 1;
+        self.collected += collected;
+        collected
+    }
+}
+
+impl Context {
+    /// Free cells that aren't reachable from the environment or a pinned
+    /// value, returning how many were freed. This also happens on its own
+    /// between evaluations
+    pub fn collect_garbage(&mut self) -> usize {
+        self.collect_from_roots(None)
+    }
+
+    // Callbacks of processes are roots as well, and so is the value being
+    // returned when collecting after an evaluation
+    pub(crate) fn collect_from_roots(&mut self, result: Option<&Expr>) -> usize {
+        let roots = self
+            .environment
+            .values()
+            .chain(self.processes.callbacks())
+            .chain(&self.pinned)
+            .chain(result);
+        self.heap.collect(roots)
+    }
+
+    /// Keep the cells in a value alive while the host holds on to it, for
+    /// example a cell returned by an evaluation that isn't bound to anything.
+    /// Every pin needs an unpin
+    pub fn pin(&mut self, expr: &Expr) {
+        self.pinned.push(expr.clone());
+    }
+
+    pub fn unpin(&mut self, expr: &Expr) {
+        if let Some(index) = self.pinned.iter().position(|it| it == expr) {
+            self.pinned.swap_remove(index);
+        }
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        HeapStats {
+            live: self.heap.live(),
+            capacity: self.heap.slots.len(),
+            collections: self.heap.collections,
+            collected: self.heap.collected,
+        }
+    }
+
+    // Value of (gc-stats), (:live n :capacity n :collections n :collected n)
+    pub(crate) fn gc_stats(&self) -> Expr {
+        let stats = self.heap_stats();
+        let number = |it: usize| Expr::Constant(Atom::Number(BigInt::from(it)));
+        let keyword = |it: &str| Expr::Constant(Atom::Keyword(it.to_string()));
+        Expr::Quote(List::from(vec![
+            keyword("live"),
+            number(stats.live),
+            keyword("capacity"),
+            number(stats.capacity),
+            keyword("collections"),
+            number(stats.collections),
+            keyword("collected"),
+            number(stats.collected),
+        ]))
+    }
+}
+
+/// Cell functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("cell", [value]) => Expr::Cell(context.heap.allocate(value.clone())),
+        ("cell-get", [Expr::Cell(cell)]) => context.heap.get(*cell)?,
+        ("cell-set", [Expr::Cell(cell), value]) => {
+            context.heap.set(*cell, value.clone())?;
+            value.clone()
+        }
+        ("cell-get" | "cell-set", [it, ..]) => bail!("{name} expects a cell, found {it}"),
+        ("gc-stats", []) => context.gc_stats(),
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,136 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+// Bump whenever images written by an older version can no longer be read
+const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Image {
+    version: u32,
+    environment: BTreeMap<String, Expr>,
+    heap: heap::Heap,
+    definitions: BTreeMap<String, Definition>,
+}
+
+// Lazy sequences hold Rust code, so they can't be written to an image.
+// Cells are saved with the heap, so they are checked on their own
+pub(crate) fn is_savable(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lazy(_) => false,
+        Expr::Call(head, tail, _) => is_savable(head) && tail.iter().all(is_savable),
+        Expr::If(predicate, then, otherwise) => {
+            is_savable(predicate) && is_savable(then) && otherwise.iter().all(|it| is_savable(it))
+        }
+        Expr::Quote(items) => items.iter().all(is_savable),
+        Expr::Let(items, _) => items.iter().all(|it| is_savable(&it.1)),
+        Expr::Function(_, body) => is_savable(body),
+        Expr::Constant(_) | Expr::Cell(_) | Expr::Coroutine(_) | Expr::Regex(_) | Expr::Nil => true,
+    }
+}
+
+impl Context {
+    /// Write every binding, functions included, as a versioned JSON image.
+    /// Bindings holding lazy sequences are left out
+    #[throws]
+    pub fn save_image(&self, writer: impl Write) {
+        let environment = self
+            .environment
+            .iter()
+            .filter(|(_, expr)| is_savable(expr))
+            .map(|(name, expr)| (name.to_string(), expr.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let image = Image {
+            version: VERSION,
+            heap: self.heap.savable(),
+            definitions: self
+                .definitions
+                .iter()
+                // Documentation of left out bindings is left out with them
+                .filter(|(name, _)| {
+                    environment.contains_key(name.as_str()) || !self.environment.contains_key(name)
+                })
+                .map(|(name, definition)| (name.to_string(), definition.clone()))
+                .collect(),
+            environment,
+        };
+        serde_json::to_writer_pretty(writer, &image)?;
+    }
+
+    /// Replace the environment and documentation with the ones stored in an
+    /// image
+    #[throws]
+    pub fn load_image(&mut self, reader: impl Read) {
+        // The version is checked first, since older images have other fields
+        let image: serde_json::Value = serde_json::from_reader(reader)?;
+        let version = image.get("version").and_then(|it| it.as_u64());
+        match version {
+            Some(version) if version == VERSION as u64 => {}
+            Some(version) => bail!("Expected image version {VERSION}, found version {version}"),
+            None => bail!("Expected image version {VERSION}, found no version"),
+        }
+        let image: Image = serde_json::from_value(image)?;
+        self.environment = image
+            .environment
+            .into_iter()
+            .map(|(name, expr)| (Symbol::new(&name), expr))
+            .collect();
+        self.heap = image.heap;
+        self.heap.restore();
+        self.definitions = image
+            .definitions
+            .into_iter()
+            .map(|(name, definition)| (Symbol::new(&name), definition))
+            .collect();
+    }
+}
+
+// Numbers are stored as decimal strings, so images don't depend on how
+// BigInt lays out its digits
+pub(crate) mod number {
+    use num::bigint::BigInt;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(number: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&number.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        let digits = String::deserialize(deserializer)?;
+        digits.parse().map_err(D::Error::custom)
+    }
+}
+
+// JSON has no inf or nan, so those are stored as strings
+pub(crate) mod float {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Float {
+        Finite(f64),
+        Named(String),
+    }
+
+    pub fn serialize<S: Serializer>(float: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match *float {
+            float if float.is_finite() => serializer.serialize_f64(float),
+            float if float.is_nan() => serializer.serialize_str("nan"),
+            float if float > 0.0 => serializer.serialize_str("inf"),
+            _ => serializer.serialize_str("-inf"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Float::deserialize(deserializer)? {
+            Float::Finite(float) => Ok(float),
+            Float::Named(name) => match name.as_str() {
+                "nan" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("invalid float: {name}"))),
+            },
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::*;
+use num::{Integer, Num, ToPrimitive, Zero};
+
+// Largest number pow and shift-left make, so a typo can't use up all memory
+const MAX_BITS: u64 = 1 << 24;
+
+#[throws]
+fn integer(name: &str, expr: &Expr) -> BigInt {
+    match expr {
+        Expr::Constant(Atom::Number(number)) => number.clone(),
+        _ => bail!("{name} expects integers, found {expr}"),
+    }
+}
+
+#[throws]
+fn count(expr: &Expr) -> usize {
+    match expr {
+        Expr::Constant(Atom::Number(number)) if let Some(count) = number.to_usize() => count,
+        _ => bail!("Expected a positive number, found {expr}"),
+    }
+}
+
+#[throws]
+fn radix(expr: &Expr) -> u32 {
+    match expr {
+        Expr::Constant(Atom::Number(number)) if let Some(radix @ 2..=36) = number.to_u32() => radix,
+        _ => bail!("Expected a radix from 2 to 36, found {expr}"),
+    }
+}
+
+#[throws]
+fn check_size(name: &str, bits: u64) {
+    if bits > MAX_BITS {
+        bail!("{name} would make a number with more than {MAX_BITS} bits");
+    }
+}
+
+fn number(number: BigInt) -> Expr {
+    Expr::Constant(Atom::Number(number))
+}
+
+fn string(string: String) -> Expr {
+    Expr::Constant(Atom::String(string))
+}
+
+/// Integer functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        // Takes the sign of the divisor, (mod -7 2) is 1
+        ("mod", [a, b]) => {
+            let (a, b) = (integer(name, a)?, integer(name, b)?);
+            if b.is_zero() {
+                bail!("mod expects a divisor other than 0");
+            }
+            number(a.mod_floor(&b))
+        }
+        ("pow", [base, exponent]) => {
+            let (base, exponent) = (integer(name, base)?, count(exponent)?);
+            // 0, 1 and -1 stay that small whatever the exponent is
+            if base.bits() > 1 {
+                check_size(name, base.bits().saturating_mul(exponent as u64))?;
+            }
+            number(num::pow(base, exponent))
+        }
+        ("gcd" | "lcm" | "bit-and" | "bit-or" | "bit-xor", [first, rest @ ..]) => {
+            let mut output = integer(name, first)?;
+            for it in rest {
+                let it = integer(name, it)?;
+                output = match name {
+                    "gcd" => output.gcd(&it),
+                    "lcm" => output.lcm(&it),
+                    "bit-and" => output & it,
+                    "bit-or" => output | it,
+                    _ => output ^ it,
+                };
+            }
+            number(output)
+        }
+        ("shift-left", [n, bits]) => {
+            let (n, bits) = (integer(name, n)?, count(bits)?);
+            check_size(name, n.bits().saturating_add(bits as u64))?;
+            number(n << bits)
+        }
+        ("shift-right", [n, bits]) => number(integer(name, n)? >> count(bits)?),
+        ("to-string", [Expr::Constant(Atom::Number(n)), base]) => {
+            string(n.to_str_radix(radix(base)?))
+        }
+        ("to-string", [it, _]) => bail!("to-string expects a number with a radix, found {it}"),
+        ("to-string", [Expr::Constant(Atom::String(it))]) => string(it.clone()),
+        ("to-string", [it]) => string(it.to_string()),
+        // nil if the string isn't a number
+        ("parse-number", [Expr::Constant(Atom::String(it)), rest @ ..]) => {
+            let parsed = match rest {
+                [base] => {
+                    let digits = it.trim().replace('_', "");
+                    BigInt::from_str_radix(&digits, radix(base)?)
+                        .ok()
+                        .map(Atom::Number)
+                }
+                _ => parse::number(it.trim()),
+            };
+            parsed.map(Expr::Constant).unwrap_or(Expr::Nil)
+        }
+        ("parse-number", [it, ..]) => bail!("parse-number expects a string, found {it}"),
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
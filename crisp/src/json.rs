@@ -0,0 +1,134 @@
+use crate::eval::{boolean_to_expr, expr_to_boolean};
+use crate::*;
+use num::ToPrimitive;
+use serde_json::{Map, Number, Value};
+
+// Options come as :keyword value pairs after the value
+#[throws]
+fn option(name: &str, tail: &[Expr], option: &str, default: bool) -> bool {
+    let mut enabled = default;
+    for pair in tail.chunks(2) {
+        match pair {
+            [Expr::Constant(Atom::Keyword(key)), value] if key == option => {
+                enabled = expr_to_boolean(value)?
+            }
+            [Expr::Constant(Atom::Keyword(key)), _] => bail!("{name} doesn't take :{key}"),
+            [key] => bail!("{name} expects a value after {key}"),
+            [key, _] => bail!("{name} expects a keyword, found {key}"),
+            _ => unreachable!(),
+        }
+    }
+    enabled
+}
+
+// Empty arrays and objects are '() rather than nil, so they encode as []
+fn list(items: Vec<Expr>) -> Expr {
+    Expr::Quote(List::from(items))
+}
+
+// Objects become property lists like (:name "crisp" :version 1), with
+// keyword keys unless strings are asked for. false and null are both nil
+fn decode(value: Value, keywords: bool) -> Expr {
+    match value {
+        Value::Null | Value::Bool(false) => Expr::Nil,
+        Value::Bool(true) => boolean_to_expr(true),
+        Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(number), _) => Expr::Constant(Atom::Number(BigInt::from(number))),
+            (_, Some(number)) => Expr::Constant(Atom::Number(BigInt::from(number))),
+            _ => Expr::Constant(Atom::Float(number.as_f64().unwrap_or(f64::NAN))),
+        },
+        Value::String(string) => Expr::Constant(Atom::String(string)),
+        Value::Array(items) => list(items.into_iter().map(|it| decode(it, keywords)).collect()),
+        Value::Object(entries) => list(
+            entries
+                .into_iter()
+                .flat_map(|(key, value)| {
+                    let key = match keywords {
+                        true => Atom::Keyword(key),
+                        false => Atom::String(key),
+                    };
+                    [Expr::Constant(key), decode(value, keywords)]
+                })
+                .collect(),
+        ),
+    }
+}
+
+// Lists of keyword and value pairs are objects, other lists are arrays
+#[throws]
+fn sequence(items: &[Expr]) -> Value {
+    let is_object = !items.is_empty()
+        && items.len().is_multiple_of(2)
+        && items
+            .iter()
+            .step_by(2)
+            .all(|it| matches!(it, Expr::Constant(Atom::Keyword(_))));
+    match is_object {
+        true => {
+            let mut entries = Map::new();
+            for pair in items.chunks(2) {
+                if let Expr::Constant(Atom::Keyword(key)) = &pair[0] {
+                    entries.insert(key.clone(), encode(&pair[1])?);
+                }
+            }
+            Value::Object(entries)
+        }
+        false => Value::Array(items.iter().map(encode).collect::<Result<_, _>>()?),
+    }
+}
+
+#[throws]
+fn encode(expr: &Expr) -> Value {
+    match expr {
+        Expr::Nil => Value::Null,
+        Expr::Constant(Atom::Symbol(symbol)) if symbol.as_str() == "T" => Value::Bool(true),
+        Expr::Constant(Atom::Number(number)) => match (number.to_i64(), number.to_u64()) {
+            (Some(number), _) => Value::from(number),
+            (_, Some(number)) => Value::from(number),
+            _ => bail!("json-encode can't encode {number}, it's too large"),
+        },
+        Expr::Constant(Atom::Float(float)) => match Number::from_f64(*float) {
+            Some(number) => Value::Number(number),
+            None => bail!("json-encode can't encode {expr}"),
+        },
+        Expr::Constant(Atom::String(string)) => Value::String(string.clone()),
+        Expr::Constant(Atom::Char(char)) => Value::String(char.to_string()),
+        Expr::Constant(Atom::Keyword(keyword)) => Value::String(keyword.clone()),
+        Expr::Constant(Atom::Symbol(symbol)) => Value::String(symbol.as_str().to_string()),
+        Expr::Quote(items) => sequence(items)?,
+        // Lists inside quoted lists are still calls
+        Expr::Call(head, tail, _) => {
+            let items = [(**head).clone()]
+                .into_iter()
+                .chain(tail.iter().cloned())
+                .collect::<Vec<_>>();
+            sequence(&items)?
+        }
+        _ => bail!("json-encode can't encode {expr}"),
+    }
+}
+
+/// JSON functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("json-parse", [Expr::Constant(Atom::String(text)), rest @ ..]) => {
+            let keywords = option(name, rest, "keywords", true)?;
+            match serde_json::from_str(text) {
+                Ok(value) => decode(value, keywords),
+                Err(error) => bail!("Invalid JSON: {error}"),
+            }
+        }
+        ("json-parse", [text, ..]) => bail!("json-parse expects a string, found {text}"),
+        ("json-encode", [value, rest @ ..]) => {
+            let value = encode(value)?;
+            let text = match option(name, rest, "pretty", false)? {
+                true => serde_json::to_string_pretty(&value)?,
+                false => serde_json::to_string(&value)?,
+            };
+            Expr::Constant(Atom::String(text))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,271 @@
+use crate::*;
+use num::One;
+
+/// Iterator protocol for lazy sequences, for instance over a buffer or a
+/// directory listing. Sequences are cloned before they are walked, so the
+/// same sequence gives the same elements every time
+pub trait Sequence: CloneSequence + Send + Sync {
+    /// Next element, or None when the sequence has ended
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error>;
+
+    /// Visit every value the sequence holds on to, so cells they refer to
+    /// aren't collected
+    fn trace(&self, _visit: &mut dyn FnMut(&Expr)) {}
+}
+
+#[doc(hidden)]
+pub trait CloneSequence {
+    fn clone_sequence(&self) -> Box<dyn Sequence>;
+}
+
+impl<T: Sequence + Clone + 'static> CloneSequence for T {
+    fn clone_sequence(&self) -> Box<dyn Sequence> {
+        Box::new(self.clone())
+    }
+}
+
+/// Lazy sequence as a crisp value, made with `Expr::Lazy(Lazy::new(sequence))`
+pub struct Lazy(Box<dyn Sequence>);
+
+impl Lazy {
+    pub fn new(sequence: impl Sequence + 'static) -> Self {
+        Self(Box::new(sequence))
+    }
+}
+
+impl Lazy {
+    pub(crate) fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        self.0.trace(visit)
+    }
+}
+
+impl Clone for Lazy {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_sequence())
+    }
+}
+
+impl std::fmt::Debug for Lazy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Lazy")
+    }
+}
+
+// Elements of any sequence, one at a time
+#[derive(Clone)]
+pub(crate) enum Items {
+    List(List, usize),
+    Lazy(Lazy),
+}
+
+impl Items {
+    /// Lists, nil, strings as sequences of characters and lazy sequences.
+    /// Lists inside quoted lists are still calls
+    #[throws]
+    pub(crate) fn new(expr: &Expr) -> Self {
+        let items = match expr {
+            Expr::Lazy(lazy) => return Self::Lazy(lazy.clone()),
+            Expr::Quote(items) => return Self::List(items.clone(), 0),
+            Expr::Call(head, tail, _) => [(**head).clone()]
+                .into_iter()
+                .chain(tail.iter().cloned())
+                .collect(),
+            Expr::Nil => Vec::new(),
+            Expr::Constant(Atom::String(string)) => string
+                .chars()
+                .map(|it| Expr::Constant(Atom::Char(it)))
+                .collect(),
+            _ => bail!("Expected a sequence, found {expr}"),
+        };
+        Self::List(List::from(items), 0)
+    }
+
+    pub(crate) fn is_lazy(&self) -> bool {
+        matches!(self, Self::Lazy(_))
+    }
+
+    #[throws]
+    pub(crate) fn next(&mut self, context: &mut Context) -> Option<Expr> {
+        match self {
+            Self::List(items, index) => {
+                *index += 1;
+                items.get(*index - 1).cloned()
+            }
+            Self::Lazy(lazy) => lazy.0.next(context)?,
+        }
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        match self {
+            Self::List(items, _) => items.iter().for_each(visit),
+            Self::Lazy(lazy) => lazy.trace(visit),
+        }
+    }
+
+    /// Every element, which never returns for infinite sequences
+    #[throws]
+    pub(crate) fn collect(mut self, context: &mut Context) -> Vec<Expr> {
+        let mut items = Vec::new();
+        while let Some(item) = self.next(context)? {
+            items.push(item);
+        }
+        items
+    }
+}
+
+// (map f seq...), ending with the shortest sequence
+#[derive(Clone)]
+pub(crate) struct Map {
+    pub(crate) function: Expr,
+    pub(crate) sequences: Vec<Items>,
+}
+
+impl Sequence for Map {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        let mut arguments = Vec::new();
+        for sequence in &mut self.sequences {
+            match sequence.next(context)? {
+                Some(item) => arguments.push(item),
+                None => return Ok(None),
+            }
+        }
+        context.call(self.function.clone(), arguments).map(Some)
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        visit(&self.function);
+        self.sequences.iter().for_each(|it| it.trace(visit));
+    }
+}
+
+// (zip seq...), ending with the shortest sequence
+#[derive(Clone)]
+pub(crate) struct Zip(pub(crate) Vec<Items>);
+
+impl Sequence for Zip {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        let mut items = Vec::new();
+        for sequence in &mut self.0 {
+            match sequence.next(context)? {
+                Some(item) => items.push(item),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(Expr::Quote(List::from(items))))
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        self.0.iter().for_each(|it| it.trace(visit));
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Filter {
+    pub(crate) predicate: Expr,
+    pub(crate) sequence: Items,
+}
+
+impl Sequence for Filter {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        while let Some(item) = self.sequence.next(context)? {
+            let keep = context.call(self.predicate.clone(), vec![item.clone()])?;
+            if eval::expr_to_boolean(&keep)? {
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        visit(&self.predicate);
+        self.sequence.trace(visit);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Drop {
+    pub(crate) count: usize,
+    pub(crate) sequence: Items,
+}
+
+impl Sequence for Drop {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        while self.count > 0 {
+            self.count -= 1;
+            if self.sequence.next(context)?.is_none() {
+                return Ok(None);
+            }
+        }
+        self.sequence.next(context)
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        self.sequence.trace(visit);
+    }
+}
+
+// (range) counting up forever
+#[derive(Clone)]
+pub(crate) struct Count(pub(crate) BigInt);
+
+impl Sequence for Count {
+    fn next(&mut self, _: &mut Context) -> Result<Option<Expr>, Error> {
+        let number = self.0.clone();
+        self.0 += BigInt::one();
+        Ok(Some(Expr::Constant(Atom::Number(number))))
+    }
+}
+
+// (iterate f x) gives x, (f x), (f (f x)) and so on
+#[derive(Clone)]
+pub(crate) struct Iterate {
+    pub(crate) function: Expr,
+    pub(crate) value: Expr,
+    pub(crate) started: bool,
+}
+
+impl Sequence for Iterate {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        if self.started {
+            self.value = context.call(self.function.clone(), vec![self.value.clone()])?;
+        }
+        self.started = true;
+        Ok(Some(self.value.clone()))
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        visit(&self.function);
+        visit(&self.value);
+    }
+}
+
+// (unfold f seed) where (f seed) gives (value next-seed), or nil to stop
+#[derive(Clone)]
+pub(crate) struct Unfold {
+    pub(crate) function: Expr,
+    pub(crate) seed: Option<Expr>,
+}
+
+impl Sequence for Unfold {
+    fn next(&mut self, context: &mut Context) -> Result<Option<Expr>, Error> {
+        let seed = match self.seed.take() {
+            Some(seed) => seed,
+            None => return Ok(None),
+        };
+        match context.call(self.function.clone(), vec![seed])? {
+            Expr::Nil => Ok(None),
+            pair => match Items::new(&pair)?.collect(context)?.as_slice() {
+                [value, seed] => {
+                    self.seed = Some(seed.clone());
+                    Ok(Some(value.clone()))
+                }
+                _ => bail!("unfold expects (value next-seed) or nil, found {pair}"),
+            },
+        }
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Expr)) {
+        visit(&self.function);
+        self.seed.iter().for_each(visit);
+    }
+}
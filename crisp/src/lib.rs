@@ -1,19 +1,56 @@
 #![feature(if_let_guard)]
+// Stable on newer toolchains, the feature keeps older nightlies building
+#![allow(stable_features)]
 
 mod eval;
 mod parse;
+mod capability;
 mod core;
+mod coroutine;
+mod debug;
+mod doc;
+mod format;
+mod fs;
+mod heap;
+mod image;
+mod int;
+mod json;
+mod lazy;
+mod lint;
+mod list;
+mod math;
+mod output;
+mod pattern;
+mod process;
+mod profile;
+mod random;
+mod symbol;
+pub mod cst;
 
 use anyhow::bail;
+pub use capability::Capability;
+pub use debug::{Backtrace, Breakpoint, Debugger, Frame, Pause, Step};
+pub use doc::Definition;
 pub use eval::Context;
+pub use format::format;
+pub use heap::{Cell, HeapStats};
+pub use lazy::{CloneSequence, Lazy, Sequence};
+pub use lint::{lint, Diagnostic, Severity};
+pub use list::List;
+pub use output::Output;
+pub use pattern::Regex;
+pub use profile::{CountingAllocator, Entry, Profile};
 pub use fehler::throws;
-pub use parse::parse;
+pub use parse::{parse, parse_recovering, Form, Recovered, SyntaxError};
+pub use symbol::Symbol;
 use std::fmt::Display;
+use std::sync::Arc;
 use num::bigint::BigInt;
 pub type Error = anyhow::Error;
 use beau_collector::BeauCollector as _;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum BuiltIn {
     Plus,
     Minus,
@@ -50,13 +87,13 @@ impl Display for BuiltIn {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Atom {
-    Number(BigInt),
-    Float(f64),
+    Number(#[serde(with = "image::number")] BigInt),
+    Float(#[serde(with = "image::float")] f64),
     Keyword(String),
     BuiltIn(BuiltIn),
-    Symbol(String),
+    Symbol(Symbol),
     String(String),
     Char(char),
 }
@@ -65,7 +102,9 @@ impl Display for Atom {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Number(number) => write!(f, "{number}"),
-            Self::Float(float) => write!(f, "{float}"),
+            // Always with a dot or an exponent, so floats read back as floats
+            Self::Float(float) if float.is_nan() => write!(f, "nan"),
+            Self::Float(float) => write!(f, "{float:?}"),
             Self::Keyword(keyword) => write!(f, ":{keyword}"),
             Self::BuiltIn(built_in) => write!(f, "{built_in}"),
             Self::Symbol(symbol) => write!(f, "{symbol}"),
@@ -75,20 +114,54 @@ impl Display for Atom {
     }
 }
 
+/// Byte range into the source text
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Line and column where this span starts, both counting from 1
+    pub fn location(&self, input: &str) -> (usize, usize) {
+        let before = &input[..self.start.min(input.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|it| *it != '\n').count() + 1;
+        (line, column)
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
 // Expressions
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expr {
     Constant(Atom),
     /// (func-name arg1 arg2 arg3 ...)
-    Call(Box<Expr>, Vec<Expr>),
+    Call(Box<Expr>, Vec<Expr>, Span),
     /// (if predicate then otherwise)
     If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
     /// '(3 (if (+ 3 3) 4 5) 7)
-    Quote(Vec<Expr>),
-    /// (let red 123)
-    Let(Vec<(Atom, Box<Expr>)>),
+    Quote(List),
+    /// (let red 123 "Documentation")
+    Let(Vec<(Atom, Box<Expr>, Option<String>)>, Span),
     /// (fn (x y z) (+ x y z))
-    Function(Vec<Expr>, Box<Expr>),
+    Function(Vec<Expr>, Arc<Expr>),
+    /// (map f (range)), elements are only evaluated when they are needed
+    #[serde(skip)]
+    Lazy(Lazy),
+    /// (cell 0), holds a value that can be changed
+    Cell(Cell),
+    /// (coroutine f), a call that can be suspended with yield and resumed
+    Coroutine(Cell),
+    /// (regex "[a-z]+")
+    Regex(Regex),
     /// nil
     Nil,
 }
@@ -97,7 +170,7 @@ impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Constant(atom) => write!(f, "{atom}"),
-            Self::Call(head, tail) => {
+            Self::Call(head, tail, _) => {
                 write!(f, "({head}")?;
                 for expr in tail {
                     write!(f, " {expr}")?;
@@ -124,13 +197,16 @@ impl Display for Expr {
                     write!(f, ")")
                 }
             },
-            Self::Let(items) => {
+            Self::Let(items, _) => {
                 write!(f, "(let ")?;
                 for (i, item) in items.iter().enumerate() {
                     if i > 0 {
                         write!(f, " ")?;
                     }
                     write!(f, "{} {}", item.0, item.1)?;
+                    if let Some(doc) = &item.2 {
+                        write!(f, " \"{doc}\"")?;
+                    }
                 }
                 write!(f, ")")
             }
@@ -144,11 +220,34 @@ impl Display for Expr {
                 }
                 write!(f, ") {body})")
             }
+            Self::Lazy(_) => write!(f, "<sequence>"),
+            Self::Cell(_) => write!(f, "<cell>"),
+            Self::Coroutine(_) => write!(f, "<coroutine>"),
+            Self::Regex(regex) => write!(f, "<regex \"{}\">", regex.as_str()),
             Self::Nil => write!(f, "nil"),
         }
     }
 }
 
+// Where a call was written doesn't change what it is, so spans are ignored
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Constant(a), Self::Constant(b)) => a == b,
+            (Self::Call(a, b, _), Self::Call(c, d, _)) => a == c && b == d,
+            (Self::If(a, b, c), Self::If(d, e, f)) => a == d && b == e && c == f,
+            (Self::Quote(a), Self::Quote(b)) => a == b,
+            (Self::Let(a, _), Self::Let(b, _)) => a == b,
+            (Self::Function(a, b), Self::Function(c, d)) => a == c && b == d,
+            (Self::Cell(a), Self::Cell(b)) => a == b,
+            (Self::Coroutine(a), Self::Coroutine(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a == b,
+            (Self::Nil, Self::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
 // Collect all errors using https://github.com/tarquin-the-brave/beau-collector/
 pub fn parse_and_eval<'a>(
     input: &'a str,
@@ -0,0 +1,357 @@
+use crate::cst::{self, Element, Node, NodeKind, TokenKind};
+use crate::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+const SPECIAL_FORMS: &[&str] = &["if", "let", "fn", "nil"];
+
+struct Definition {
+    span: Span,
+    /// Maximum amount of arguments, if this is a function
+    arity: Option<usize>,
+    used: bool,
+}
+
+#[derive(Default)]
+struct Linter<'a> {
+    globals: HashMap<&'a str, Definition>,
+    /// Parameters of the functions we are inside, innermost last
+    scopes: Vec<Vec<(&'a str, Definition)>>,
+    /// Name of the function being defined, so recursion doesn't count as use
+    defining: Vec<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// Elements of a list without the parens and trivia
+fn elements(node: &Node) -> Vec<&Element> {
+    node.significant()
+        .filter(|it| match it {
+            Element::Token(token) => {
+                !matches!(token.kind, TokenKind::LeftParen | TokenKind::RightParen)
+            }
+            Element::Node(_) => true,
+        })
+        .collect()
+}
+
+fn symbol(element: &Element) -> Option<&str> {
+    match element {
+        Element::Token(token) if token.kind == TokenKind::Symbol => Some(&token.text),
+        _ => None,
+    }
+}
+
+fn list(element: &Element) -> Option<&Node> {
+    match element {
+        Element::Node(node) if node.kind == NodeKind::List => Some(node),
+        _ => None,
+    }
+}
+
+// Head symbol of a list, (let ...) gives let
+fn head(element: &Element) -> Option<&str> {
+    list(element).and_then(|node| elements(node).first().and_then(|it| symbol(it)))
+}
+
+fn is_string(element: &Element) -> bool {
+    matches!(element, Element::Token(token) if token.kind == TokenKind::String)
+}
+
+// Body of a function, skipping the docstring in (fn (x) "doc" body)
+fn body<'a>(forms: &[&'a Element]) -> Option<&'a Element> {
+    match forms {
+        [doc, body, ..] if is_string(doc) => Some(body),
+        [body, ..] => Some(body),
+        [] => None,
+    }
+}
+
+// Parameters of (fn (x y) body), along with the body
+fn function(element: &Element) -> Option<(Vec<&Element>, Option<&Element>)> {
+    match head(element) {
+        Some("fn") => {
+            let forms = elements(list(element)?);
+            let params = forms.get(1).and_then(|it| list(it)).map(elements)?;
+            Some((params, body(forms.get(2..).unwrap_or_default())))
+        }
+        _ => None,
+    }
+}
+
+// Names and values of (let x 10 "doc" (f x) "doc" body), without docstrings
+fn bindings<'a>(forms: &[&'a Element]) -> Vec<(&'a Element, Option<&'a Element>)> {
+    let mut bindings = Vec::new();
+    let mut index = 0;
+    while let Some(name) = forms.get(index) {
+        let rest = &forms[index + 1..];
+        let (value, length) = match list(name) {
+            Some(_) if rest.len() > 1 && is_string(rest[0]) => (body(rest), 2),
+            Some(_) => (rest.first().copied(), 1),
+            None if rest.get(1).is_some_and(|it| is_string(it)) => (rest.first().copied(), 2),
+            None => (rest.first().copied(), 1),
+        };
+        bindings.push((*name, value));
+        index += 1 + length;
+    }
+    bindings
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, severity: Severity, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            span,
+            message,
+        });
+    }
+
+    fn shadows(&mut self, name: &str, span: Span) {
+        let builtin = core::FUNCTIONS.iter().any(|(it, _)| *it == name);
+        if builtin || SPECIAL_FORMS.contains(&name) {
+            self.report(Severity::Warning, span, format!("Shadowed builtin: {name}"));
+        }
+    }
+
+    // Global definitions can be used anywhere, so collect them up front
+    fn define(&mut self, element: &'a Element) {
+        let node = match list(element) {
+            Some(node) => node,
+            None => return,
+        };
+        let forms = elements(node);
+        if head(element) == Some("let") {
+            for (name, value) in bindings(&forms[1..]) {
+                let (name, arity) = match list(name) {
+                    Some(lambda) => {
+                        let lambda = elements(lambda);
+                        (
+                            lambda.first().copied(),
+                            Some(lambda.len().saturating_sub(1)),
+                        )
+                    }
+                    None => {
+                        let arity = value.and_then(function).map(|(it, _)| it.len());
+                        (Some(name), arity)
+                    }
+                };
+                if let Some(token) = name.and_then(|it| symbol(it).map(|text| (text, it.span()))) {
+                    self.shadows(token.0, token.1);
+                    self.globals.insert(
+                        token.0,
+                        Definition {
+                            span: token.1,
+                            arity,
+                            used: false,
+                        },
+                    );
+                }
+            }
+        }
+        for form in forms {
+            self.define(form);
+        }
+    }
+
+    fn scope(&mut self, params: &[&'a Element]) {
+        let mut scope = Vec::new();
+        for param in params {
+            if let Some(name) = symbol(param) {
+                self.shadows(name, param.span());
+                let definition = Definition {
+                    span: param.span(),
+                    arity: None,
+                    used: false,
+                };
+                scope.push((name, definition));
+            }
+        }
+        self.scopes.push(scope);
+    }
+
+    fn unscope(&mut self) {
+        for (name, definition) in self.scopes.pop().unwrap_or_default() {
+            if !definition.used {
+                let message = format!("Unused parameter: {name}");
+                self.report(Severity::Warning, definition.span, message);
+            }
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Definition> {
+        let local = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|(it, _)| *it == name);
+        match local {
+            Some((_, definition)) => Some(definition),
+            None => self.globals.get_mut(name),
+        }
+    }
+
+    fn reference(&mut self, name: &str, span: Span, context: &Context) {
+        let recursive = self.defining.contains(&name);
+        match self.lookup(name) {
+            Some(definition) => definition.used |= !recursive,
+            None if name == "nil" || context.is_bound(name) => {}
+            None if core::FUNCTIONS.iter().any(|(it, _)| *it == name) => {}
+            None => self.report(Severity::Error, span, format!("Unbound symbol: {name}")),
+        }
+    }
+
+    fn arity(&mut self, head: &Element, arguments: usize, span: Span) {
+        let name = match head {
+            Element::Token(token) if token.text == "!" => {
+                if arguments != 1 {
+                    let message = format!("! expects 1 parameter, got {arguments}");
+                    self.report(Severity::Error, span, message);
+                }
+                return;
+            }
+            element => symbol(element),
+        };
+        let builtin = name.and_then(|name| core::FUNCTIONS.iter().find(|(it, _)| *it == name));
+        let defined = name.and_then(|name| self.lookup(name).map(|it| it.arity));
+        match (defined, builtin) {
+            (Some(Some(arity)), _) if arguments > arity => self.report(
+                Severity::Error,
+                span,
+                format!("Expected maximum {arity} arguments, got {arguments}"),
+            ),
+            (None, Some((name, arity))) if !arity.contains(&arguments) => self.report(
+                Severity::Error,
+                span,
+                format!("{}, got {arguments}", core::expects(name, arity)),
+            ),
+            _ => {}
+        }
+    }
+
+    fn check(&mut self, element: &'a Element, context: &Context) {
+        let node = match element {
+            Element::Token(token) => {
+                if token.kind == TokenKind::Symbol {
+                    self.reference(&token.text, token.span, context);
+                }
+                return;
+            }
+            Element::Node(node) => node,
+        };
+        if node.kind != NodeKind::List {
+            // Quoted data and datum comments are never evaluated
+            return;
+        }
+        let forms = elements(node);
+        match head(element) {
+            Some("if") => {
+                if forms.len() == 3 {
+                    let message = "Missing else branch, fails when the predicate is false";
+                    self.report(Severity::Warning, node.span, message.to_string());
+                }
+                for form in &forms[1..] {
+                    self.check(form, context);
+                }
+            }
+            Some("let") => {
+                for (name, value) in bindings(&forms[1..]) {
+                    match list(name).map(elements) {
+                        Some(lambda) => {
+                            let name = lambda.first().and_then(|it| symbol(it));
+                            self.defining.push(name.unwrap_or_default());
+                            self.scope(lambda.get(1..).unwrap_or_default());
+                            if let Some(it) = value {
+                                self.check(it, context);
+                            }
+                            self.unscope();
+                            self.defining.pop();
+                        }
+                        None => {
+                            self.defining.push(symbol(name).unwrap_or_default());
+                            if let Some(it) = value {
+                                self.check(it, context);
+                            }
+                            self.defining.pop();
+                        }
+                    }
+                }
+            }
+            Some("fn") => {
+                if let Some((params, body)) = function(element) {
+                    self.scope(&params);
+                    if let Some(it) = body {
+                        self.check(it, context);
+                    }
+                    self.unscope();
+                }
+            }
+            _ => {
+                if let Some(head) = forms.first() {
+                    self.arity(head, forms.len() - 1, node.span);
+                }
+                for form in forms {
+                    self.check(form, context);
+                }
+            }
+        }
+    }
+}
+
+/// Find likely mistakes without evaluating anything. Symbols bound in the
+/// context count as defined, so earlier evaluations aren't reported
+pub fn lint(input: &str, context: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics = parse_recovering(input)
+        .errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            severity: Severity::Error,
+            span: error.span,
+            message: error.message,
+        })
+        .collect::<Vec<_>>();
+
+    let root = cst::parse(input);
+    let mut linter = Linter::default();
+    for element in root.significant() {
+        linter.define(element);
+    }
+    for element in root.significant() {
+        linter.check(element, context);
+    }
+    let mut unused = linter
+        .globals
+        .iter()
+        .filter(|(_, definition)| !definition.used)
+        .map(|(name, definition)| Diagnostic {
+            severity: Severity::Warning,
+            span: definition.span,
+            message: format!("Unused binding: {name}"),
+        })
+        .collect::<Vec<_>>();
+    diagnostics.append(&mut linter.diagnostics);
+    diagnostics.append(&mut unused);
+    diagnostics.sort_by_key(|it| it.span.start);
+    diagnostics
+}
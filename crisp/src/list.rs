@@ -0,0 +1,75 @@
+use crate::Expr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Elements of a quoted list. Cloning only bumps a reference count, and
+/// tails share the elements of the list they were taken from
+#[derive(Clone, Default)]
+pub struct List {
+    items: Arc<Vec<Expr>>,
+    start: usize,
+}
+
+impl List {
+    /// Everything after the first `count` elements
+    pub fn tail(&self, count: usize) -> Self {
+        Self {
+            items: self.items.clone(),
+            start: (self.start + count).min(self.items.len()),
+        }
+    }
+
+    // Copies the elements if they are shared
+    pub(crate) fn make_mut(&mut self) -> &mut [Expr] {
+        let start = self.start;
+        &mut Arc::make_mut(&mut self.items)[start..]
+    }
+}
+
+impl Deref for List {
+    type Target = [Expr];
+
+    fn deref(&self) -> &[Expr] {
+        &self.items[self.start..]
+    }
+}
+
+impl From<Vec<Expr>> for List {
+    fn from(items: Vec<Expr>) -> Self {
+        Self {
+            items: Arc::new(items),
+            start: 0,
+        }
+    }
+}
+
+impl FromIterator<Expr> for List {
+    fn from_iter<T: IntoIterator<Item = Expr>>(iter: T) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl std::fmt::Debug for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Serialize for List {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for List {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from(Vec::deserialize(deserializer)?))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::*;
+use num::{FromPrimitive, ToPrimitive};
+
+type Unary = fn(f64) -> f64;
+
+const UNARY: &[(&str, Unary)] = &[
+    ("sqrt", f64::sqrt),
+    ("exp", f64::exp),
+    ("ln", f64::ln),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+];
+
+// Enough for any float to read back the same, longer ones only add noise and
+// would let (format-float x 1000000000) allocate without bound
+const MAX_DIGITS: usize = 17;
+
+// Integers are turned into floats, so (sqrt 2) works
+#[throws]
+fn float(name: &str, expr: &Expr) -> f64 {
+    match expr {
+        Expr::Constant(Atom::Float(float)) => *float,
+        Expr::Constant(Atom::Number(number)) => number.to_f64().unwrap_or(f64::NAN),
+        _ => bail!("{name} expects a number, found {expr}"),
+    }
+}
+
+#[throws]
+fn integer(float: f64) -> Expr {
+    match BigInt::from_f64(float) {
+        Some(number) => Expr::Constant(Atom::Number(number)),
+        None => bail!("Can't turn {} into an integer", Atom::Float(float)),
+    }
+}
+
+/// Math functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    if let Some((_, function)) = UNARY.iter().find(|(it, _)| *it == name) {
+        let output = function(float(name, &tail[0])?);
+        return Ok(Some(Expr::Constant(Atom::Float(output))));
+    }
+    let output = match (name, tail) {
+        ("log", [x]) => Expr::Constant(Atom::Float(float(name, x)?.log10())),
+        ("log", [x, base]) => Expr::Constant(Atom::Float(float(name, x)?.log(float(name, base)?))),
+        ("atan2", [y, x]) => Expr::Constant(Atom::Float(float(name, y)?.atan2(float(name, x)?))),
+        // Integers are already whole
+        (
+            "floor" | "ceil" | "round" | "truncate" | "int",
+            [it @ Expr::Constant(Atom::Number(_))],
+        ) => it.clone(),
+        ("floor", [x]) => integer(float(name, x)?.floor())?,
+        ("ceil", [x]) => integer(float(name, x)?.ceil())?,
+        // Halfway cases round away from zero, (round 2.5) is 3
+        ("round", [x]) => integer(float(name, x)?.round())?,
+        ("truncate" | "int", [x]) => integer(float(name, x)?.trunc())?,
+        ("float", [x]) => Expr::Constant(Atom::Float(float(name, x)?)),
+        ("format-float", [x, digits]) => {
+            let digits = match digits {
+                Expr::Constant(Atom::Number(number)) if let Some(digits) = number.to_usize() => {
+                    digits
+                }
+                _ => bail!("format-float expects a positive number of digits, found {digits}"),
+            };
+            if digits > MAX_DIGITS {
+                bail!("format-float expects at most {MAX_DIGITS} digits, found {digits}");
+            }
+            Expr::Constant(Atom::String(format!("{:.*}", digits, float(name, x)?)))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,115 @@
+use crate::*;
+use std::io::Write;
+
+/// Where print and println write to
+#[derive(Default)]
+pub enum Output {
+    #[default]
+    Stdout,
+    /// Collect the text, which the host takes with `Context::take_output`
+    Buffer(String),
+    Writer(Box<dyn Write>),
+}
+
+impl Output {
+    #[throws]
+    fn write(&mut self, text: &str) {
+        match self {
+            Output::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(text.as_bytes())?;
+                stdout.flush()?;
+            }
+            Output::Buffer(buffer) => buffer.push_str(text),
+            Output::Writer(writer) => {
+                writer.write_all(text.as_bytes())?;
+                writer.flush()?;
+            }
+        }
+    }
+}
+
+impl Context {
+    /// Send what is printed somewhere else. Returns the previous output
+    pub fn set_output(&mut self, output: Output) -> Output {
+        std::mem::replace(&mut self.output, output)
+    }
+
+    /// Text printed since the last call, when printing to a buffer
+    pub fn take_output(&mut self) -> String {
+        match &mut self.output {
+            Output::Buffer(buffer) => std::mem::take(buffer),
+            _ => String::new(),
+        }
+    }
+}
+
+// Like Rust, {} shows strings without quotes and {:?} shows them as written.
+// Braces are escaped by doubling them
+#[throws]
+fn format(name: &str, template: &Expr, arguments: &[Expr]) -> String {
+    let template = match template {
+        Expr::Constant(Atom::String(template)) => template,
+        _ => bail!("{name} expects a format string, found {template}"),
+    };
+    let mut output = String::new();
+    let count = arguments.len();
+    let mut arguments = arguments.iter();
+    let mut placeholders = 0;
+    let mut rest = template.as_str();
+    while let Some(index) = rest.find(['{', '}']) {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let (debug, length) = if rest.starts_with("{}") {
+            (false, 2)
+        } else if rest.starts_with("{:?}") {
+            (true, 4)
+        } else {
+            bail!("Invalid format string: {template}")
+        };
+        placeholders += 1;
+        match arguments.next() {
+            Some(Expr::Constant(Atom::String(string))) if !debug => output.push_str(string),
+            Some(Expr::Constant(Atom::Char(char))) if !debug => output.push(*char),
+            Some(expr) => output.push_str(&expr.to_string()),
+            None => {}
+        }
+        rest = &rest[length..];
+    }
+    output.push_str(rest);
+    if placeholders != count {
+        bail!("{name} has {placeholders} placeholder(s), found {count} argument(s)");
+    }
+    output
+}
+
+/// Output functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("format", [template, arguments @ ..]) => {
+            Expr::Constant(Atom::String(format(name, template, arguments)?))
+        }
+        ("print", [template, arguments @ ..]) => {
+            let text = format(name, template, arguments)?;
+            context.output.write(&text)?;
+            Expr::Nil
+        }
+        ("println", []) => {
+            context.output.write("\n")?;
+            Expr::Nil
+        }
+        ("println", [template, arguments @ ..]) => {
+            let text = format(name, template, arguments)? + "\n";
+            context.output.write(&text)?;
+            Expr::Nil
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -2,16 +2,24 @@ use crate::*;
 use nom::{
     branch::alt,
     bytes::complete::{take, take_until},
-    character::complete::{alpha1, alphanumeric1, char, digit1, multispace0},
-    combinator::{cut, map, map_res, opt, recognize},
+    character::complete::{
+        alpha1, alphanumeric1, anychar, char, multispace1, not_line_ending, one_of, satisfy,
+    },
+    combinator::{all_consuming, cut, eof, map, map_res, not, opt, peek, recognize, value},
     multi::{many0, many1},
-    sequence::{delimited, pair, preceded, tuple},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     Parser,
 };
-use nom_supreme::{error::ErrorTree, final_parser::final_parser, tag::complete::tag, ParserExt};
+use nom_supreme::{
+    error::{BaseErrorKind, ErrorTree, StackContext},
+    final_parser::final_parser,
+    tag::complete::tag,
+    ParserExt,
+};
+use num::Num;
 
 // Helpers
-type IResult<'a, T, U> = nom::IResult<T, U, ErrorTree<&'a str>>;
+pub(crate) type IResult<'a, T, U> = nom::IResult<T, U, ErrorTree<&'a str>>;
 
 fn sexp<'a, O1, F>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O1>
 where
@@ -19,27 +27,86 @@ where
 {
     delimited(
         char('('),
-        preceded(multispace0, inner),
-        cut(preceded(multispace0, char(')'))),
+        preceded(trivia, inner),
+        cut(preceded(trivia, char(')'))),
     )
 }
 
-fn ws<'a, F: 'a, O>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+fn ws<'a, F, O>(inner: F) -> impl FnMut(&'a str) -> IResult<'a, &'a str, O>
 where
-    F: FnMut(&'a str) -> IResult<&'a str, O>,
+    F: FnMut(&'a str) -> IResult<'a, &'a str, O> + 'a,
 {
-    delimited(multispace0, inner, multispace0)
+    delimited(trivia, inner, trivia)
 }
 
 fn lambda(args: Vec<Atom>, expr: Expr) -> Expr {
     Expr::Function(
         args.into_iter().map(Expr::Constant).collect(),
-        Box::new(expr),
+        Arc::new(expr),
     )
 }
 
+// Comments
+#[derive(Debug)]
+struct CommentError(&'static str);
+
+impl Display for CommentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CommentError {}
+
+// Fail at the start of a broken comment, rather than wherever parsing it
+// gave up
+fn comment_error<'a>(message: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    cut(map_res(take(0usize), move |_| Err(CommentError(message))))
+}
+
+pub(crate) fn line_comment(input: &str) -> IResult<'_, &str, &str> {
+    recognize(pair(char(';'), not_line_ending))(input)
+}
+
+// Block comments nest, so #| #| inner |# outer |# is a single comment
+pub(crate) fn block_comment(input: &str) -> IResult<'_, &str, &str> {
+    let body = many0(alt((
+        block_comment,
+        recognize(preceded(not(alt((tag("#|"), tag("|#")))), anychar)),
+    )));
+    let closed = recognize(preceded(tag("#|"), pair(body, tag("|#"))));
+    let unclosed = preceded(peek(tag("#|")), comment_error("unclosed block comment"));
+    alt((closed, unclosed))(input)
+}
+
+// Datum comments skip the next expression, #_(this is ignored)
+fn datum_comment(input: &str) -> IResult<'_, &str, &str> {
+    // Nothing to skip before a ) or the end of input
+    let empty = preceded(
+        peek(tuple((tag("#_"), trivia, alt((tag(")"), eof))))),
+        comment_error("datum comment needs a form"),
+    );
+    let comment = recognize(preceded(
+        tag("#_"),
+        cut(parse_expr).context("datum comment"),
+    ));
+    alt((empty, comment))(input)
+}
+
+fn trivia(input: &str) -> IResult<'_, &str, ()> {
+    value(
+        (),
+        many0(alt((
+            multispace1,
+            line_comment,
+            block_comment,
+            datum_comment,
+        ))),
+    )(input)
+}
+
 // Atoms
-fn parse_built_in(input: &str) -> IResult<&str, Atom> {
+pub(crate) fn parse_built_in(input: &str) -> IResult<'_, &str, Atom> {
     map(
         alt((
             map(tag("+"), |_| BuiltIn::Plus),
@@ -57,18 +124,18 @@ fn parse_built_in(input: &str) -> IResult<&str, Atom> {
             map(tag("||"), |_| BuiltIn::Or),
         ))
         .context("operator"),
-        |built_in| Atom::BuiltIn(built_in),
+        Atom::BuiltIn,
     )(input)
 }
 
-fn parse_keyword(input: &str) -> IResult<&str, Atom> {
+pub(crate) fn parse_keyword(input: &str) -> IResult<'_, &str, Atom> {
     map(
         preceded(tag(":"), cut(alpha1)).context("keyword"),
         |keyword: &str| Atom::Keyword(keyword.to_string()),
     )(input)
 }
 
-fn parse_symbol(input: &str) -> IResult<&str, Atom> {
+pub(crate) fn parse_symbol(input: &str) -> IResult<'_, &str, Atom> {
     let identifier = recognize(pair(
         alpha1,
         many0(alt((
@@ -80,30 +147,102 @@ fn parse_symbol(input: &str) -> IResult<&str, Atom> {
             tag("?"),
         ))),
     ));
-    map(identifier, |symbol: &str| Atom::Symbol(symbol.to_string()))(input)
+    map(identifier, |symbol: &str| Atom::Symbol(Symbol::new(symbol)))(input)
 }
 
-fn parse_number(input: &str) -> IResult<&str, Atom> {
-    alt((
-        map_res(digit1, |digits: &str| {
-            digits.parse::<BigInt>().map(Atom::Number)
-        }),
-        map_res(preceded(tag("-"), digit1), |digits: &str| {
-            digits.parse::<BigInt>().map(|it| Atom::Number(it * -1))
-        }),
+#[derive(Debug)]
+struct NumberName(String);
+
+impl Display for NumberName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is a number and can't be used as a name", self.0)
+    }
+}
+
+impl std::error::Error for NumberName {}
+
+// Names of bindings and parameters. inf and nan read as floats, so binding
+// them would silently do nothing
+fn parse_name(input: &str) -> IResult<'_, &str, Atom> {
+    let number = preceded(
+        peek(alt((parse_float, parse_number))),
+        cut(map_res(
+            recognize(alt((parse_float, parse_number))),
+            |it: &str| Err::<Atom, _>(NumberName(it.to_string())),
+        )),
+    );
+    alt((number, parse_symbol))(input)
+}
+
+// Digits in a radix, which can be separated by underscores like 1_000
+fn digits<'a>(radix: u32) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    recognize(pair(
+        satisfy(move |it| it.is_digit(radix)),
+        many0(satisfy(move |it| it.is_digit(radix) || it == '_')),
     ))
+}
+
+pub(crate) fn parse_number(input: &str) -> IResult<'_, &str, Atom> {
+    let prefixed = |prefix, radix| {
+        map(preceded(tag(prefix), cut(digits(radix))), move |it| {
+            (it, radix)
+        })
+    };
+    map_res(
+        pair(
+            opt(char('-')),
+            alt((
+                prefixed("0x", 16),
+                prefixed("0b", 2),
+                prefixed("0o", 8),
+                map(digits(10), |it| (it, 10)),
+            )),
+        ),
+        |(sign, (digits, radix))| {
+            BigInt::from_str_radix(&digits.replace('_', ""), radix).map(|it| match sign {
+                Some(_) => Atom::Number(-it),
+                None => Atom::Number(it),
+            })
+        },
+    )
     .context("number")
     .parse(input)
 }
 
-fn parse_float(input: &str) -> IResult<&str, Atom> {
+// 1.5, .5, 1e10 and 2.5e-3, as well as inf and nan as long as they aren't the
+// start of a longer symbol like info. Decimals can't run into a symbol either,
+// so 1.5e is an error rather than 1.5 followed by e
+pub(crate) fn parse_float(input: &str) -> IResult<'_, &str, Atom> {
+    let boundary = || not(satisfy(|it| it.is_alphanumeric() || "_-<>?.".contains(it)));
+    let exponent = || tuple((one_of("eE"), opt(one_of("+-")), digits(10)));
+    let fraction = alt((
+        recognize(tuple((digits(10), char('.'), digits(10)))),
+        recognize(pair(char('.'), digits(10))),
+    ));
+    let decimal = terminated(
+        alt((
+            recognize(pair(fraction, opt(exponent()))),
+            recognize(pair(digits(10), exponent())),
+        )),
+        cut(boundary()),
+    );
+    let special = terminated(alt((tag("inf"), tag("nan"))), boundary());
     map_res(
-        recognize(tuple((parse_number, char('.'), parse_number))),
-        |float| float.parse::<f64>().map(Atom::Float),
-    )(input)
+        recognize(pair(opt(char('-')), alt((decimal, special)))),
+        |float: &str| float.replace('_', "").parse::<f64>().map(Atom::Float),
+    )
+    .context("float")
+    .parse(input)
 }
 
-fn parse_string(input: &str) -> IResult<&str, Atom> {
+/// Number or float spelled like a literal, like "0xff" or "1_000.5"
+pub(crate) fn number(input: &str) -> Option<Atom> {
+    all_consuming(alt((parse_float, parse_number)))(input)
+        .ok()
+        .map(|(_, number)| number)
+}
+
+pub(crate) fn parse_string(input: &str) -> IResult<'_, &str, Atom> {
     map(
         delimited(
             tag("\""),
@@ -115,7 +254,7 @@ fn parse_string(input: &str) -> IResult<&str, Atom> {
     .parse(input)
 }
 
-fn parse_char(input: &str) -> IResult<&str, Atom> {
+pub(crate) fn parse_char(input: &str) -> IResult<'_, &str, Atom> {
     map(
         delimited(tag("'"), cut(take(1usize)).context("char"), tag("'")),
         |it: &str| Atom::Char(it.chars().next().unwrap()),
@@ -123,7 +262,7 @@ fn parse_char(input: &str) -> IResult<&str, Atom> {
     .parse(input)
 }
 
-fn parse_atom(input: &str) -> IResult<&str, Atom> {
+fn parse_atom(input: &str) -> IResult<'_, &str, Atom> {
     alt((
         parse_string,
         parse_char,
@@ -135,22 +274,50 @@ fn parse_atom(input: &str) -> IResult<&str, Atom> {
     ))(input)
 }
 
-fn parse_nil(input: &str) -> IResult<&str, Expr> {
+fn parse_nil(input: &str) -> IResult<'_, &str, Expr> {
     map(tag("nil"), |_| Expr::Nil)(input)
 }
 
-fn parse_constant(input: &str) -> IResult<&str, Expr> {
-    map(parse_atom, |atom| Expr::Constant(atom))(input)
+fn parse_constant(input: &str) -> IResult<'_, &str, Expr> {
+    map(parse_atom, Expr::Constant)(input)
 }
 
-fn parse_call(input: &str) -> IResult<&str, Expr> {
-    let inner = map(tuple((parse_expr, many0(parse_expr))), |(head, tail)| {
-        Expr::Call(Box::new(head), tail)
-    });
-    sexp(inner)(input)
+// Spans count from the end of the input while parsing, `locate` turns them
+// into offsets once the length of the whole input is known
+fn parse_call(input: &str) -> IResult<'_, &str, Expr> {
+    let (rest, (head, tail)) = sexp(tuple((parse_expr, many0(parse_expr))))(input)?;
+    let span = Span::new(input.len(), rest.len());
+    Ok((rest, Expr::Call(Box::new(head), tail, span)))
 }
 
-fn parse_if(input: &str) -> IResult<&str, Expr> {
+fn locate(expr: &mut Expr, length: usize) {
+    match expr {
+        Expr::Call(head, tail, span) => {
+            *span = Span::new(length - span.start, length - span.end);
+            locate(head, length);
+            tail.iter_mut().for_each(|it| locate(it, length));
+        }
+        Expr::If(predicate, then, otherwise) => {
+            locate(predicate, length);
+            locate(then, length);
+            otherwise.iter_mut().for_each(|it| locate(it, length));
+        }
+        Expr::Quote(items) => items
+            .make_mut()
+            .iter_mut()
+            .for_each(|it| locate(it, length)),
+        Expr::Let(items, span) => {
+            *span = Span::new(length - span.start, length - span.end);
+            items.iter_mut().for_each(|it| locate(&mut it.1, length));
+        }
+        Expr::Function(_, body) => locate(Arc::make_mut(body), length),
+        Expr::Constant(_) | Expr::Lazy(_) | Expr::Cell(_) | Expr::Coroutine(_)
+        | Expr::Regex(_)
+        | Expr::Nil => {}
+    }
+}
+
+fn parse_if(input: &str) -> IResult<'_, &str, Expr> {
     sexp(map(
         preceded(
             ws(tag("if")),
@@ -162,43 +329,89 @@ fn parse_if(input: &str) -> IResult<&str, Expr> {
     ))(input)
 }
 
-fn parse_quote(input: &str) -> IResult<&str, Expr> {
+fn parse_quote(input: &str) -> IResult<'_, &str, Expr> {
     let single = map(preceded(tag("'"), parse_expr), |expr| {
-        Expr::Quote(vec![expr])
+        Expr::Quote(List::from(vec![expr]))
     });
     let many = map(preceded(tag("'"), sexp(many0(parse_expr))), |exprs| {
-        Expr::Quote(exprs)
+        Expr::Quote(List::from(exprs))
     });
     alt((many, single))(input)
 }
 
-fn parse_let(input: &str) -> IResult<&str, Expr> {
-    let regular = tuple((parse_symbol, map(parse_expr, Box::new)));
-    let lambda = map(
+fn parse_docstring(input: &str) -> IResult<'_, &str, String> {
+    map(ws(parse_string), |it| match it {
+        Atom::String(string) => string,
+        _ => unreachable!(),
+    })(input)
+}
+
+// A string between the parameters and the body of a function documents it
+fn parse_body(input: &str) -> IResult<'_, &str, (Option<String>, Expr)> {
+    alt((
+        map(tuple((parse_docstring, parse_expr)), |(doc, body)| {
+            (Some(doc), body)
+        }),
+        map(parse_expr, |body| (None, body)),
+    ))(input)
+}
+
+type Binding = (Atom, Box<Expr>, Option<String>);
+
+// Variables are documented with a string after their value, (let x 10 "doc")
+fn parse_binding(input: &str) -> IResult<'_, &str, Binding> {
+    let regular = map(
         tuple((
-            sexp(tuple((ws(parse_symbol), many0(ws(parse_symbol))))),
-            parse_expr,
+            parse_name,
+            alt((
+                ws(parse_documented_function),
+                map(parse_expr, |it| (it, None)),
+            )),
+            opt(parse_docstring),
         )),
-        |((name, args), body)| (name, Box::new(lambda(args, body))),
+        |(name, (value, inner), doc)| (name, Box::new(value), doc.or(inner)),
     );
-    sexp(map(
-        preceded(ws(tag("let")), many1(ws(alt((lambda, regular))))),
-        |items| Expr::Let(items),
-    ))(input)
+    // A string before the body only documents the function when the body
+    // ends the let, so (let (f) "a" g 5) binds f to "a". Functions before
+    // the last are documented with a string after their body instead
+    let body = alt((
+        map(
+            terminated(tuple((parse_docstring, parse_expr)), peek(ws(char(')')))),
+            |(doc, body)| (Some(doc), body),
+        ),
+        map(pair(parse_expr, opt(parse_docstring)), |(body, doc)| {
+            (doc, body)
+        }),
+    ));
+    let lambda = map(
+        tuple((sexp(tuple((ws(parse_name), many0(ws(parse_name))))), body)),
+        |((name, args), (doc, body))| (name, Box::new(lambda(args, body)), doc),
+    );
+    alt((lambda, regular))(input)
+}
+
+fn parse_let(input: &str) -> IResult<'_, &str, Expr> {
+    let (rest, items) = sexp(preceded(ws(tag("let")), many1(ws(parse_binding))))(input)?;
+    let span = Span::new(input.len(), rest.len());
+    Ok((rest, Expr::Let(items, span)))
 }
 
-fn parse_function(input: &str) -> IResult<&str, Expr> {
+fn parse_documented_function(input: &str) -> IResult<'_, &str, (Expr, Option<String>)> {
     sexp(map(
         preceded(
             ws(tag("fn")),
-            cut(tuple((sexp(many0(ws(parse_symbol))), parse_expr))),
+            cut(tuple((sexp(many0(ws(parse_name))), parse_body))),
         ),
-        |(args, body)| lambda(args, body),
+        |(args, (doc, body))| (lambda(args, body), doc),
     ))(input)
 }
 
-fn parse_expr(input: &str) -> IResult<&str, Expr> {
-    ws(alt((
+fn parse_function(input: &str) -> IResult<'_, &str, Expr> {
+    map(parse_documented_function, |(function, _)| function)(input)
+}
+
+fn parse_form(input: &str) -> IResult<'_, &str, Expr> {
+    alt((
         parse_quote,
         parse_nil,
         parse_constant,
@@ -206,9 +419,177 @@ fn parse_expr(input: &str) -> IResult<&str, Expr> {
         parse_let,
         parse_function,
         parse_call,
-    )))(input)
+    ))(input)
+}
+
+fn parse_expr(input: &str) -> IResult<'_, &str, Expr> {
+    ws(parse_form)(input)
 }
 
 pub fn parse(input: &str) -> Result<Vec<Expr>, ErrorTree<&str>> {
-    final_parser(many1(parse_expr))(&input)
+    let mut exprs = final_parser(many1(parse_expr))(input)?;
+    exprs.iter_mut().for_each(|it| locate(it, input.len()));
+    Ok(exprs)
+}
+
+// Error recovery
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Form {
+    Expr(Expr, Span),
+    /// Region that could not be parsed, see the matching `SyntaxError`
+    Error(Span),
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Recovered {
+    pub forms: Vec<Form>,
+    /// One for each Form::Error, in the same order
+    pub errors: Vec<SyntaxError>,
+}
+
+impl Recovered {
+    pub fn exprs(&self) -> impl Iterator<Item = &Expr> {
+        self.forms.iter().filter_map(|form| match form {
+            Form::Expr(expr, _) => Some(expr),
+            Form::Error(_) => None,
+        })
+    }
+}
+
+// Find the error that got furthest into the input, since that is usually
+// where the actual mistake is
+fn furthest<'a>(error: &ErrorTree<&'a str>) -> (&'a str, String) {
+    match error {
+        ErrorTree::Base { location, kind } => {
+            let message = match kind {
+                BaseErrorKind::Expected(expectation) => format!("expected {expectation}"),
+                BaseErrorKind::External(error) => format!("{error}"),
+                BaseErrorKind::Kind(_) => match location.chars().next() {
+                    Some(letter) => format!("unexpected '{letter}'"),
+                    None => String::from("unexpected end of input"),
+                },
+            };
+            (location, message)
+        }
+        ErrorTree::Stack { base, contexts } => {
+            let (location, message) = furthest(base);
+            let context = contexts.iter().find_map(|(_, context)| match context {
+                StackContext::Context(context) => Some(context),
+                StackContext::Kind(_) => None,
+            });
+            match context {
+                Some(context) => (location, format!("{message} in {context}")),
+                None => (location, message),
+            }
+        }
+        ErrorTree::Alt(siblings) => {
+            let errors = siblings.iter().map(furthest).collect::<Vec<_>>();
+            let remaining = errors.iter().map(|(location, _)| location.len()).min();
+            let mut candidates = errors
+                .into_iter()
+                .filter(|(location, _)| Some(location.len()) == remaining);
+            match (candidates.next(), candidates.next()) {
+                (Some(error), None) => error,
+                // No alternative got further than the others
+                (Some((location, _)), Some(_)) => match location.chars().next() {
+                    Some(letter) => (location, format!("unexpected '{letter}'")),
+                    None => (location, String::from("unexpected end of input")),
+                },
+                (None, _) => ("", String::from("invalid syntax")),
+            }
+        }
+    }
+}
+
+// Length of the broken region at the start of input. Balanced lists are
+// skipped as a whole, otherwise skip until the next line that starts a form
+fn broken_region(input: &str) -> usize {
+    let next_form = |from: usize| {
+        input[from..]
+            .find("\n(")
+            .map(|it| from + it + 1)
+            .unwrap_or(input.len())
+    };
+    let mut chars = input.char_indices().peekable();
+    let mut depth = 0;
+    while let Some((index, letter)) = chars.next() {
+        match letter {
+            '(' => depth += 1,
+            ')' if depth == 0 => return index.max(1),
+            ')' if depth == 1 => return index + 1,
+            ')' => depth -= 1,
+            '\'' if input[index + 1..].chars().nth(1) == Some('\'') => {
+                chars.nth(1);
+            }
+            '"' => match input[index + 1..].find('"') {
+                Some(end) => while chars.next_if(|(it, _)| *it <= index + 1 + end).is_some() {},
+                None => return input.len(),
+            },
+            ';' => while chars.next_if(|(_, it)| *it != '\n').is_some() {},
+            '#' if chars.next_if(|(_, it)| *it == '|').is_some() => match input[index..].find("|#")
+            {
+                Some(end) => while chars.next_if(|(it, _)| *it < index + end + 2).is_some() {},
+                None => return input.len(),
+            },
+            letter if depth == 0 && letter.is_whitespace() => return index,
+            _ => {}
+        }
+    }
+    match depth {
+        0 => input.len(),
+        _ => next_form(1),
+    }
+}
+
+/// Parse as many top-level forms as possible, collecting every syntax error
+/// instead of stopping at the first one
+pub fn parse_recovering(input: &str) -> Recovered {
+    let offset = |rest: &str| input.len() - rest.len();
+    let mut recovered = Recovered::default();
+    let mut rest = input;
+    loop {
+        let result = trivia(rest).and_then(|(rest, _)| match rest.is_empty() {
+            true => Ok((rest, None)),
+            false => parse_form(rest).map(|(next, expr)| (next, Some((rest, expr)))),
+        });
+        match result {
+            Ok((_, None)) => return recovered,
+            Ok((next, Some((start, mut expr)))) => {
+                locate(&mut expr, input.len());
+                let span = Span::new(offset(start), offset(next));
+                recovered.forms.push(Form::Expr(expr, span));
+                rest = next;
+            }
+            Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+                // Skip the form that contains the error
+                let (form, _) = trivia(rest).unwrap_or((rest, ()));
+                let length = broken_region(form).max(1).min(form.len());
+                let region = Span::new(offset(form), offset(form) + length);
+
+                // Every broken region gets an error inside of it. Errors past
+                // its end come from a list that was never closed
+                let (location, message) = furthest(&error);
+                let (location, message) = match offset(location) < region.end {
+                    true => (location, message),
+                    false if form.starts_with('(') => (form, String::from("unclosed '('")),
+                    false => (form, message),
+                };
+                let start = offset(location);
+                let width = location.chars().next().map(char::len_utf8).unwrap_or(0);
+                recovered.errors.push(SyntaxError {
+                    span: Span::new(start, start + width),
+                    message,
+                });
+                recovered.forms.push(Form::Error(region));
+                rest = &form[length..];
+            }
+            Err(nom::Err::Incomplete(_)) => return recovered,
+        }
+    }
 }
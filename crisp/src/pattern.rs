@@ -0,0 +1,176 @@
+use crate::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+// Patterns are compiled once and reused, until there are this many
+const CACHED: usize = 256;
+
+/// Compiled regular expression, made with (regex "pattern")
+#[derive(Debug, Clone)]
+pub struct Regex(regex::Regex);
+
+impl Regex {
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+// Images store the pattern and compile it again when they are loaded
+impl Serialize for Regex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Regex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        regex::Regex::new(&pattern)
+            .map(Regex)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Patterns(HashMap<String, Regex>);
+
+impl Patterns {
+    #[throws]
+    fn compile(&mut self, pattern: &str) -> Regex {
+        if let Some(regex) = self.0.get(pattern) {
+            return regex.clone();
+        }
+        let regex = match regex::Regex::new(pattern) {
+            Ok(regex) => Regex(regex),
+            Err(error) => bail!("Invalid regex: {error}"),
+        };
+        if self.0.len() >= CACHED {
+            self.0.clear();
+        }
+        self.0.insert(pattern.to_string(), regex.clone());
+        regex
+    }
+}
+
+// Capture as (text start end) with byte offsets, or nil if it didn't take part
+fn capture(capture: Option<regex::Match>) -> Expr {
+    match capture {
+        Some(capture) => Expr::Quote(List::from(vec![
+            Expr::Constant(Atom::String(capture.as_str().to_string())),
+            Expr::Constant(Atom::Number(BigInt::from(capture.start()))),
+            Expr::Constant(Atom::Number(BigInt::from(capture.end()))),
+        ])),
+        None => Expr::Nil,
+    }
+}
+
+// Every capture of a match, the whole match first
+fn captures(captures: &regex::Captures) -> Expr {
+    Expr::Quote(List::from(captures.iter().map(capture).collect::<Vec<_>>()))
+}
+
+fn list(items: Vec<Expr>) -> Expr {
+    match items.is_empty() {
+        true => Expr::Nil,
+        false => Expr::Quote(List::from(items)),
+    }
+}
+
+impl Context {
+    /// Regex value or a pattern string, which is compiled once per context
+    #[throws]
+    fn regex(&mut self, name: &str, expr: &Expr) -> Regex {
+        match expr {
+            Expr::Regex(regex) => regex.clone(),
+            Expr::Constant(Atom::String(pattern)) => self.patterns.compile(pattern)?,
+            _ => bail!("{name} expects a regex or a string, found {expr}"),
+        }
+    }
+}
+
+#[throws]
+fn text<'a>(name: &str, expr: &'a Expr) -> &'a str {
+    match expr {
+        Expr::Constant(Atom::String(text)) => text.as_str(),
+        _ => bail!("{name} expects a string, found {expr}"),
+    }
+}
+
+/// Regex functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        ("regex", [pattern]) => Expr::Regex(context.regex(name, pattern)?),
+        // Whole string has to match
+        ("re-match", [regex, string]) => {
+            let regex = context.regex(name, regex)?;
+            let whole = context
+                .patterns
+                .compile(&format!("^(?:{})$", regex.as_str()))?;
+            match whole.0.captures(text(name, string)?) {
+                Some(found) => captures(&found),
+                None => Expr::Nil,
+            }
+        }
+        ("re-find", [regex, string]) => {
+            let regex = context.regex(name, regex)?;
+            match regex.0.captures(text(name, string)?) {
+                Some(found) => captures(&found),
+                None => Expr::Nil,
+            }
+        }
+        ("re-find-all", [regex, string]) => {
+            let regex = context.regex(name, regex)?;
+            let string = text(name, string)?;
+            list(
+                regex
+                    .0
+                    .captures_iter(string)
+                    .map(|it| captures(&it))
+                    .collect(),
+            )
+        }
+        // Replacements can refer to captures like $1, or be a function that
+        // is called with the captures of every match
+        ("re-replace", [regex, string, replacement]) => {
+            let regex = context.regex(name, regex)?;
+            let string = text(name, string)?;
+            let output = match replacement {
+                Expr::Constant(Atom::String(replacement)) => regex
+                    .0
+                    .replace_all(string, replacement.as_str())
+                    .into_owned(),
+                function => {
+                    let mut output = String::new();
+                    let mut last = 0;
+                    for found in regex.0.captures_iter(string) {
+                        let whole = found.get(0).map(|it| it.range()).unwrap_or_default();
+                        let replacement = context.call(function.clone(), vec![captures(&found)])?;
+                        output += &string[last..whole.start];
+                        output += text(name, &replacement)?;
+                        last = whole.end;
+                    }
+                    output + &string[last..]
+                }
+            };
+            Expr::Constant(Atom::String(output))
+        }
+        ("re-split", [regex, string]) => {
+            let regex = context.regex(name, regex)?;
+            let parts = regex
+                .0
+                .split(text(name, string)?)
+                .map(|it| Expr::Constant(Atom::String(it.to_string())))
+                .collect();
+            list(parts)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,302 @@
+use crate::*;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+const FUNCTIONS: &[&str] = &[
+    "call-process",
+    "shell-command",
+    "start-process",
+    "wait-process",
+    "poll-processes",
+];
+
+enum Event {
+    Output(String),
+    Error(String),
+    Exit(Option<i32>),
+}
+
+// Process started with start-process, whose output is waiting to be passed
+// to its callbacks
+struct Running {
+    on_output: Option<Expr>,
+    on_error: Option<Expr>,
+    on_exit: Option<Expr>,
+    events: Receiver<Event>,
+}
+
+/// Processes that are still running, by process id
+#[derive(Default)]
+pub(crate) struct Processes(HashMap<u32, Running>);
+
+impl Processes {
+    /// Callbacks can hold on to cells, so they are roots for the collector
+    pub(crate) fn callbacks(&self) -> impl Iterator<Item = &Expr> {
+        self.0.values().flat_map(|it| {
+            [&it.on_output, &it.on_error, &it.on_exit]
+                .into_iter()
+                .flatten()
+        })
+    }
+}
+
+#[derive(Default)]
+struct Options {
+    stdin: Option<String>,
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+    on_output: Option<Expr>,
+    on_error: Option<Expr>,
+    on_exit: Option<Expr>,
+}
+
+#[throws]
+fn text(name: &str, expr: &Expr) -> String {
+    match expr {
+        Expr::Constant(Atom::String(text)) => text.clone(),
+        _ => bail!("{name} expects a string, found {expr}"),
+    }
+}
+
+#[throws]
+fn strings(name: &str, expr: &Expr) -> Vec<String> {
+    match expr {
+        Expr::Quote(items) => items
+            .iter()
+            .map(|it| text(name, it))
+            .collect::<Result<_, _>>()?,
+        Expr::Nil => Vec::new(),
+        _ => bail!("{name} expects a list of strings, found {expr}"),
+    }
+}
+
+// Options come as :keyword value pairs, callbacks are only taken by
+// start-process
+#[throws]
+fn options(name: &str, tail: &[Expr]) -> Options {
+    let mut options = Options::default();
+    let callbacks = name == "start-process";
+    for pair in tail.chunks(2) {
+        let (key, value) = match pair {
+            [Expr::Constant(Atom::Keyword(key)), value] => (key.as_str(), value),
+            [key] => bail!("{name} expects a value after {key}"),
+            [key, _] => bail!("{name} expects a keyword, found {key}"),
+            _ => unreachable!(),
+        };
+        match key {
+            "stdin" => options.stdin = Some(text(name, value)?),
+            "cwd" => options.cwd = Some(text(name, value)?),
+            "env" => {
+                let env = strings(name, value)?;
+                if env.len() % 2 != 0 {
+                    bail!("{name} expects :env to be pairs of names and values");
+                }
+                options.env = env
+                    .chunks(2)
+                    .map(|it| (it[0].clone(), it[1].clone()))
+                    .collect();
+            }
+            "output" if callbacks => options.on_output = Some(value.clone()),
+            "error" if callbacks => options.on_error = Some(value.clone()),
+            "exit" if callbacks => options.on_exit = Some(value.clone()),
+            _ => bail!("{name} doesn't take :{key}"),
+        }
+    }
+    options
+}
+
+#[throws]
+fn spawn(name: &str, program: &str, args: &[String], options: &mut Options) -> Child {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .envs(options.env.iter().map(|(name, value)| (name, value)))
+        .stdin(match options.stdin {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &options.cwd {
+        command.current_dir(cwd);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => bail!("{name} couldn't start {program}: {error}"),
+    };
+    // Written from another thread, so a process that fills its output before
+    // reading all of its input can't block us
+    if let (Some(stdin), Some(mut pipe)) = (options.stdin.take(), child.stdin.take()) {
+        std::thread::spawn(move || pipe.write_all(stdin.as_bytes()));
+    }
+    child
+}
+
+fn text_expr(text: String) -> Expr {
+    Expr::Constant(Atom::String(text))
+}
+
+fn exit(code: Option<i32>) -> Expr {
+    match code {
+        Some(code) => Expr::Constant(Atom::Number(BigInt::from(code))),
+        None => Expr::Nil,
+    }
+}
+
+// Send output a line at a time, so characters are never split
+fn forward(pipe: impl Read, sender: Sender<Event>, event: fn(String) -> Event) {
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+    while let Ok(1..) = reader.read_until(b'\n', &mut line) {
+        let text = String::from_utf8_lossy(&line).into_owned();
+        if sender.send(event(text)).is_err() {
+            return;
+        }
+        line.clear();
+    }
+}
+
+// Read both pipes until the process closes them, then wait for it to exit
+fn watch(mut child: Child, sender: Sender<Event>) {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take().map(|stderr| {
+        let sender = sender.clone();
+        std::thread::spawn(move || forward(stderr, sender, Event::Error))
+    });
+    if let Some(stdout) = stdout {
+        forward(stdout, sender.clone(), Event::Output);
+    }
+    if let Some(stderr) = stderr {
+        let _ = stderr.join();
+    }
+    let code = child.wait().ok().and_then(|it| it.code());
+    let _ = sender.send(Event::Exit(code));
+}
+
+impl Context {
+    /// Pass output of processes started with start-process to their
+    /// callbacks, without waiting for more. Hosts call this from their event
+    /// loop. Returns how many processes are still running
+    pub fn poll_processes(&mut self) -> Result<usize, Error> {
+        let ids = self.processes.0.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.deliver(id, false)?;
+        }
+        Ok(self.processes.0.len())
+    }
+
+    // Call the callbacks of a process for its events, until there are no more
+    // or it has exited. Returns the exit code once it has
+    fn deliver(&mut self, id: u32, wait: bool) -> Result<Option<Expr>, Error> {
+        loop {
+            let running = match self.processes.0.get(&id) {
+                Some(running) => running,
+                None => return Ok(None),
+            };
+            let event = match wait {
+                true => running.events.recv().unwrap_or(Event::Exit(None)),
+                false => match running.events.try_recv() {
+                    Ok(event) => event,
+                    Err(TryRecvError::Empty) => return Ok(None),
+                    Err(TryRecvError::Disconnected) => Event::Exit(None),
+                },
+            };
+            let (callback, argument) = match event {
+                Event::Output(text) => (running.on_output.clone(), text_expr(text)),
+                Event::Error(text) => (running.on_error.clone(), text_expr(text)),
+                Event::Exit(code) => {
+                    let running = self.processes.0.remove(&id).unwrap();
+                    if let Some(callback) = running.on_exit {
+                        self.call(callback, vec![exit(code)])?;
+                    }
+                    return Ok(Some(exit(code)));
+                }
+            };
+            if let Some(callback) = callback {
+                self.call(callback, vec![argument])?;
+            }
+        }
+    }
+}
+
+// (:exit 0 :stdout "..." :stderr "...")
+#[throws]
+fn run(name: &str, program: &str, args: &[String], options: &mut Options) -> Expr {
+    let output = spawn(name, program, args, options)?.wait_with_output()?;
+    let keyword = |it: &str| Expr::Constant(Atom::Keyword(it.to_string()));
+    Expr::Quote(List::from(vec![
+        keyword("exit"),
+        exit(output.status.code()),
+        keyword("stdout"),
+        text_expr(String::from_utf8_lossy(&output.stdout).into_owned()),
+        keyword("stderr"),
+        text_expr(String::from_utf8_lossy(&output.stderr).into_owned()),
+    ]))
+}
+
+/// Process functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    if !FUNCTIONS.contains(&name) {
+        return Ok(None);
+    }
+    if cfg!(target_arch = "wasm32") {
+        bail!("{name} isn't available in the wasm build");
+    }
+    context.require(name, Capability::Process)?;
+    let output = match (name, tail) {
+        ("call-process", [program, args, rest @ ..]) => run(
+            name,
+            &text(name, program)?,
+            &strings(name, args)?,
+            &mut options(name, rest)?,
+        )?,
+        ("shell-command", [command, rest @ ..]) => {
+            let (shell, flag) = match cfg!(windows) {
+                true => ("cmd", "/C"),
+                false => ("sh", "-c"),
+            };
+            let args = [flag.to_string(), text(name, command)?];
+            run(name, shell, &args, &mut options(name, rest)?)?
+        }
+        ("start-process", [program, args, rest @ ..]) => {
+            let mut options = options(name, rest)?;
+            let child = spawn(
+                name,
+                &text(name, program)?,
+                &strings(name, args)?,
+                &mut options,
+            )?;
+            let id = child.id();
+            let (sender, events) = mpsc::channel();
+            std::thread::spawn(move || watch(child, sender));
+            let running = Running {
+                on_output: options.on_output,
+                on_error: options.on_error,
+                on_exit: options.on_exit,
+                events,
+            };
+            context.processes.0.insert(id, running);
+            Expr::Constant(Atom::Number(BigInt::from(id)))
+        }
+        ("wait-process", [id]) => {
+            let running = match id {
+                Expr::Constant(Atom::Number(number)) => u32::try_from(number)
+                    .ok()
+                    .filter(|it| context.processes.0.contains_key(it)),
+                _ => None,
+            };
+            match running {
+                Some(id) => context.deliver(id, true)?.unwrap_or(Expr::Nil),
+                None => bail!("wait-process expects a running process, found {id}"),
+            }
+        }
+        ("poll-processes", []) => {
+            Expr::Constant(Atom::Number(BigInt::from(context.poll_processes()?)))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
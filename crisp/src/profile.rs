@@ -0,0 +1,170 @@
+use crate::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Global allocator that counts allocations so profiles can attribute them
+/// to functions. Without it installed in the host, allocations stay at zero:
+///
+/// ```
+/// #[global_allocator]
+/// static ALLOCATOR: crisp::CountingAllocator = crisp::CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Totals for one function
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Entry {
+    pub calls: u64,
+    /// Time spent in the function and everything it called. Recursive calls
+    /// are only counted once
+    pub inclusive: Duration,
+    /// Time spent in the function itself
+    pub exclusive: Duration,
+    /// Allocations made by the function itself
+    pub allocations: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Profile {
+    pub functions: BTreeMap<String, Entry>,
+    /// Exclusive time per stack of calls, outermost first and separated by ;
+    pub stacks: BTreeMap<String, Duration>,
+}
+
+impl Profile {
+    /// Table of functions sorted by exclusive time, with times in milliseconds
+    pub fn report(&self) -> String {
+        let mut functions = self.functions.iter().collect::<Vec<_>>();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .chain([8])
+            .max()
+            .unwrap_or_default();
+        let mut output = format!(
+            "{:width$}  {:>8}  {:>12}  {:>12}  {:>11}\n",
+            "function", "calls", "inclusive ms", "exclusive ms", "allocations"
+        );
+        for (name, entry) in functions {
+            output += &format!(
+                "{name:width$}  {:>8}  {:>12.3}  {:>12.3}  {:>11}\n",
+                entry.calls,
+                entry.inclusive.as_secs_f64() * 1000.0,
+                entry.exclusive.as_secs_f64() * 1000.0,
+                entry.allocations
+            );
+        }
+        output
+    }
+
+    /// Folded stacks with exclusive time in microseconds, the input format of
+    /// flamegraph.pl and inferno
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, time)| format!("{stack} {}\n", time.as_micros()))
+            .collect()
+    }
+}
+
+// A call that hasn't returned yet
+struct Open {
+    name: Symbol,
+    start: Instant,
+    allocations: u64,
+    children: Duration,
+    child_allocations: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Profiler {
+    profile: Profile,
+    open: Vec<Open>,
+    /// Allocations made by the profiler itself, left out of the counts
+    overhead: u64,
+}
+
+impl Profiler {
+    fn allocations(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed) - self.overhead
+    }
+
+    pub(crate) fn enter(&mut self, name: Symbol) {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let open = Open {
+            name,
+            start: Instant::now(),
+            allocations: before - self.overhead,
+            children: Duration::ZERO,
+            child_allocations: 0,
+        };
+        self.open.push(open);
+        self.overhead += ALLOCATIONS.load(Ordering::Relaxed) - before;
+    }
+
+    pub(crate) fn exit(&mut self) {
+        let (elapsed, allocations) = match self.open.last() {
+            Some(open) => (open.start.elapsed(), self.allocations() - open.allocations),
+            None => return,
+        };
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let open = self.open.pop().unwrap();
+        let stack = self
+            .open
+            .iter()
+            .map(|it| it.name.as_str())
+            .chain([open.name.as_str()])
+            .collect::<Vec<_>>()
+            .join(";");
+        let exclusive = elapsed.saturating_sub(open.children);
+        *self.profile.stacks.entry(stack).or_default() += exclusive;
+
+        let recursive = self.open.iter().any(|it| it.name == open.name);
+        let entry = self.profile.functions.entry(open.name.to_string()).or_default();
+        entry.calls += 1;
+        entry.exclusive += exclusive;
+        entry.allocations += allocations - open.child_allocations;
+        if !recursive {
+            entry.inclusive += elapsed;
+        }
+        if let Some(parent) = self.open.last_mut() {
+            parent.children += elapsed;
+            parent.child_allocations += allocations;
+        }
+        self.overhead += ALLOCATIONS.load(Ordering::Relaxed) - before;
+    }
+}
+
+impl Context {
+    /// Start recording calls, time and allocations per function, throwing
+    /// away anything recorded before
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::default());
+    }
+
+    /// Stop recording and return the profile, if profiling was started
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        self.profiler.take().map(|it| it.profile)
+    }
+}
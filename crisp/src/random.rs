@@ -0,0 +1,137 @@
+use crate::lazy::Items;
+use crate::*;
+use num::ToPrimitive;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// SplitMix64, which is small and good enough for anything but cryptography.
+/// The same seed always gives the same numbers
+pub(crate) struct Random(u64);
+
+// Seeded from the randomness std uses for hash maps. On wasm32-unknown-unknown
+// std has no randomness and this is the same seed every time, so hosts there
+// have to pass one of their own to Context::seed_random
+impl Default for Random {
+    fn default() -> Self {
+        Random(RandomState::new().build_hasher().finish())
+    }
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..bound, rejecting the numbers that would make small
+    // results more likely
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let number = self.next();
+            if number < zone {
+                return number % bound;
+            }
+        }
+    }
+
+    // Uniform in [0, 1)
+    fn float(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Context {
+    /// Restart random numbers from a seed, so they are the same every run
+    pub fn seed_random(&mut self, seed: u64) {
+        self.random = Random(seed);
+    }
+}
+
+#[throws]
+fn integer(name: &str, expr: &Expr) -> BigInt {
+    match expr {
+        Expr::Constant(Atom::Number(number)) => number.clone(),
+        _ => bail!("{name} expects integers, found {expr}"),
+    }
+}
+
+#[throws]
+fn float(name: &str, expr: &Expr) -> f64 {
+    match expr {
+        Expr::Constant(Atom::Float(float)) => *float,
+        Expr::Constant(Atom::Number(number)) => number.to_f64().unwrap_or(f64::NAN),
+        _ => bail!("{name} expects a number, found {expr}"),
+    }
+}
+
+// Every element of a sequence, which never returns for infinite ones
+#[throws]
+fn elements(context: &mut Context, expr: &Expr) -> Vec<Expr> {
+    Items::new(expr)?.collect(context)?
+}
+
+/// Random functions, None if there is no function with that name. Arity is
+/// checked by core::std
+pub(crate) fn std(context: &mut Context, name: &str, tail: &[Expr]) -> Result<Option<Expr>, Error> {
+    let output = match (name, tail) {
+        // (random 6) is from 0 to 5, (random 1 7) from 1 to 6
+        ("random", [min @ .., max]) => {
+            let min = match min {
+                [min] => integer(name, min)?,
+                _ => BigInt::from(0),
+            };
+            let max = integer(name, max)?;
+            let bound = match (&max - &min).to_u64() {
+                Some(bound) if bound > 0 => bound,
+                _ => bail!("random expects a range from {min} to {max} that isn't empty"),
+            };
+            Expr::Constant(Atom::Number(min + context.random.below(bound)))
+        }
+        ("random-float", []) => Expr::Constant(Atom::Float(context.random.float())),
+        ("random-float", [min @ .., max]) => {
+            let min = match min {
+                [min] => float(name, min)?,
+                _ => 0.0,
+            };
+            let max = float(name, max)?;
+            let float = min + (max - min) * context.random.float();
+            Expr::Constant(Atom::Float(float))
+        }
+        ("random-choice", [items]) => {
+            let mut items = elements(context, items)?;
+            match items.len() {
+                0 => Expr::Nil,
+                length => items.swap_remove(context.random.below(length as u64) as usize),
+            }
+        }
+        // Fisher-Yates
+        ("shuffle", [items]) => {
+            let mut items = elements(context, items)?;
+            for index in (1..items.len()).rev() {
+                let other = context.random.below(index as u64 + 1) as usize;
+                items.swap(index, other);
+            }
+            match items.is_empty() {
+                true => Expr::Nil,
+                false => Expr::Quote(List::from(items)),
+            }
+        }
+        // Negative seeds are as good as any other
+        ("random-seed", [seed]) => {
+            let seed = integer(name, seed)?;
+            let seed = match (seed.to_u64(), seed.to_i64()) {
+                (Some(seed), _) => seed,
+                (_, Some(seed)) => seed as u64,
+                _ => bail!("random-seed expects a seed that fits in 64 bits, found {seed}"),
+            };
+            context.seed_random(seed);
+            Expr::Nil
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(output))
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+
+static SYMBOLS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+/// Interned name. Every symbol with the same name shares one allocation, so
+/// symbols are compared and hashed by address
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    pub fn new(name: &str) -> Self {
+        let mut symbols = SYMBOLS.get_or_init(Default::default).lock().unwrap();
+        match symbols.get(name) {
+            Some(symbol) => Self(symbol),
+            None => {
+                let symbol = Box::leak(name.to_string().into_boxed_str());
+                symbols.insert(symbol);
+                Self(symbol)
+            }
+        }
+    }
+
+    /// The symbol with this name, without interning it if there is none.
+    /// Nothing can be bound to a name that was never interned
+    pub fn get(name: &str) -> Option<Self> {
+        let symbols = SYMBOLS.get_or_init(Default::default).lock().unwrap();
+        symbols.get(name).map(|symbol| Self(symbol))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
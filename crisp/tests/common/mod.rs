@@ -0,0 +1,29 @@
+// Helpers shared by the integration tests, each test binary uses some of them
+#![allow(dead_code)]
+
+use crisp::{parse_and_eval, Capability, Context};
+
+pub fn eval(input: &str, context: &mut Context) -> Vec<String> {
+    parse_and_eval(input, context)
+        .unwrap()
+        .unwrap()
+        .iter()
+        .map(|it| it.to_string())
+        .collect()
+}
+
+pub fn error(input: &str, context: &mut Context) -> String {
+    parse_and_eval(input, context)
+        .unwrap()
+        .unwrap_err()
+        .to_string()
+}
+
+// Context with every capability, like the command line gives scripts
+pub fn trusted() -> Context {
+    let mut context = Context::default();
+    for capability in Capability::ALL {
+        context.grant(*capability);
+    }
+    context
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{error, eval};
+use crisp::Context;
+
+#[test]
+fn generators() {
+    let mut context = Context::default();
+    eval(
+        "(let (naturals n) (if (yield n) nil (naturals (+ n 1))))
+         (let (pair a b) (list (yield a) (yield b)))",
+        &mut context,
+    );
+    assert_eq!(
+        eval(
+            "(take 5 (generator naturals 0))
+             (take 3 (map (fn (x) (* x 10)) (generator pair 1 2)))
+             (generator pair 1 2)",
+            &mut context,
+        ),
+        ["(0 1 2 3 4)", "(10 20)", "<sequence>"]
+    );
+
+    // Tail calls inside generators don't grow the stack
+    assert_eq!(
+        eval("(car (drop 20000 (generator naturals 0)))", &mut context),
+        ["20000"]
+    );
+}
+
+#[test]
+fn resume() {
+    let mut context = Context::default();
+    eval(
+        "(let (total sum) (if (= sum 100) sum (total (+ sum (yield sum)))))
+         (let (twice x) (* 2 (yield x)))
+         (let (nested x) (+ 1 (twice x)))
+         (let a (coroutine total 0) b (coroutine nested 5))",
+        &mut context,
+    );
+    assert_eq!(
+        eval(
+            "(resume a)
+             (resume a 10)
+             (resume a 40)
+             (done? a)
+             (resume a 50)
+             (done? a)",
+            &mut context,
+        ),
+        ["0", "10", "50", "nil", "100", "T"]
+    );
+
+    // Functions called from a coroutine can yield for it
+    assert_eq!(
+        eval("(resume b) (resume b 20) (done? b)", &mut context),
+        ["5", "41", "T"]
+    );
+}
+
+#[test]
+fn errors() {
+    let mut context = Context::default();
+    eval(
+        "(let (once x) (yield x))
+         (let (broken x) (+ (yield x) :a))
+         (let (reentrant) (resume c))
+         (let a (coroutine once 1) b (coroutine broken 1) c (coroutine reentrant))",
+        &mut context,
+    );
+    assert_eq!(
+        error("(yield 1)", &mut context),
+        "yield can only be used inside a coroutine or generator"
+    );
+    assert_eq!(
+        error(
+            "(take 1 (map (fn (x) (yield x)) (generator once 1)))",
+            &mut context
+        ),
+        "yield can only be used inside a coroutine or generator"
+    );
+    assert_eq!(
+        error("(resume 1)", &mut context),
+        "resume expects a coroutine, found 1"
+    );
+    assert_eq!(
+        error("(resume c)", &mut context),
+        "Coroutine is already running"
+    );
+
+    eval("(resume a) (resume a)", &mut context);
+    assert_eq!(error("(resume a)", &mut context), "Coroutine has finished");
+
+    // Errors finish the coroutine
+    eval("(resume b)", &mut context);
+    assert_eq!(
+        error("(resume b 1)", &mut context),
+        "+ expects number(s) or float(s), found none"
+    );
+    assert_eq!(eval("(done? b)", &mut context), ["T"]);
+}
+
+#[test]
+fn collection() {
+    let mut context = Context::default();
+    eval(
+        "(let (hold x) (yield x))
+         (let a (coroutine hold (cell 1)) b (cell nil))
+         (cell-set b a)",
+        &mut context,
+    );
+    assert_eq!(context.collect_garbage(), 0);
+    assert_eq!(context.heap_stats().live, 3);
+
+    eval("(let a nil)", &mut context);
+    assert_eq!(context.collect_garbage(), 0);
+    eval("(let b nil)", &mut context);
+    assert_eq!(context.collect_garbage(), 3);
+}
+
+#[test]
+fn image() {
+    let mut context = Context::default();
+    eval(
+        "(let (naturals n) (if (yield n) nil (naturals (+ n 1))))
+         (let a (coroutine naturals 0))
+         (resume a)
+         (resume a)",
+        &mut context,
+    );
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+    assert_eq!(eval("(resume a) (resume a)", &mut restored), ["2", "3"]);
+}
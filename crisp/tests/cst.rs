@@ -0,0 +1,55 @@
+use crisp::cst::{self, Element, NodeKind, TokenKind};
+
+#[test]
+fn lossless() {
+    let docs = std::fs::read_to_string("../DOCS.md").unwrap();
+    let inputs = [
+        docs.as_str(),
+        "(let (triple x) ; comment\n  (* x 3))",
+        "'(1 2.50 #| block #| nested |# |# \"a;b\") #_ (skip) 'c'",
+        ") (unterminated (list \"string",
+        "(@ [1 2] #| open",
+    ];
+    for input in inputs {
+        assert_eq!(cst::parse(input).to_string(), input);
+    }
+}
+
+#[test]
+fn structure() {
+    let root = cst::parse("(f 1.0) ; done\n'(a)");
+    let kinds = root
+        .children
+        .iter()
+        .map(|it| match it {
+            Element::Node(node) => format!("{:?}", node.kind),
+            Element::Token(token) => format!("{:?}", token.kind),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        ["List", "Whitespace", "LineComment", "Whitespace", "Quote"]
+    );
+
+    let Element::Node(list) = &root.children[0] else {
+        panic!()
+    };
+    assert_eq!(list.kind, NodeKind::List);
+    assert_eq!((list.span.start, list.span.end), (0, 7));
+    let tokens = list
+        .significant()
+        .map(|it| match it {
+            Element::Token(token) => token.kind,
+            Element::Node(_) => TokenKind::Error,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        [
+            TokenKind::LeftParen,
+            TokenKind::Symbol,
+            TokenKind::Float,
+            TokenKind::RightParen
+        ]
+    );
+}
@@ -0,0 +1,140 @@
+use crisp::{parse_and_eval, Backtrace, Breakpoint, Context, Debugger, Pause, Span, Step};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+const DEFINITIONS: &str = "(let (square x) (* x x)) (let (sum a b) (+ (square a) (square b)))";
+
+// Answers pauses from a script of steps and logs where it stopped
+struct Script {
+    steps: VecDeque<Step>,
+    log: Rc<RefCell<Vec<String>>>,
+}
+
+impl Debugger for Script {
+    fn pause(&mut self, reason: Pause, context: &Context) -> Step {
+        let stack = context
+            .stack()
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect::<Vec<_>>();
+        self.log
+            .borrow_mut()
+            .push(format!("{reason:?} {}", stack.join(" > ")));
+        self.steps.pop_front().unwrap_or_default()
+    }
+}
+
+fn debug(input: &str, breakpoints: Vec<Breakpoint>, steps: Vec<Step>) -> Vec<String> {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut context = Context::default();
+    parse_and_eval(DEFINITIONS, &mut context).unwrap().unwrap();
+    context.set_debugger(Some(Box::new(Script {
+        steps: steps.into(),
+        log: log.clone(),
+    })));
+    for breakpoint in breakpoints {
+        context.add_breakpoint(breakpoint);
+    }
+    parse_and_eval(input, &mut context).unwrap().unwrap();
+    assert!(context.stack().is_empty());
+    log.take()
+}
+
+#[test]
+fn function_breakpoint() {
+    let log = debug(
+        "(sum 1 2)",
+        vec![Breakpoint::Function("square".into())],
+        vec![],
+    );
+    assert_eq!(
+        log,
+        [
+            "Breakpoint (sum 1 2) > (square 1)",
+            "Breakpoint (sum 1 2) > (square 2)",
+        ]
+    );
+}
+
+#[test]
+fn stepping() {
+    let log = debug(
+        "(sum 1 2)",
+        vec![Breakpoint::Function("sum".into())],
+        vec![Step::Into, Step::Into, Step::Over, Step::Out],
+    );
+    assert_eq!(
+        log,
+        [
+            "Breakpoint (sum 1 2)",
+            "Step (sum 1 2) > (square 1)",
+            // Tail calls replace the frame they were made from
+            "Step (sum 1 2) > (* 1 1)",
+            "Step (sum 1 2) > (square 2)",
+            "Step (+ 1 4)",
+        ]
+    );
+}
+
+#[test]
+fn span_breakpoint() {
+    // Spans are offsets into the source a call was parsed from
+    let start = DEFINITIONS.find("(* x x)").unwrap();
+    let span = Span::new(start, start + 7);
+    let log = debug("(sum 1 2)", vec![Breakpoint::Span(span)], vec![]);
+    assert_eq!(
+        log,
+        [
+            "Breakpoint (sum 1 2) > (* 1 1)",
+            "Breakpoint (sum 1 2) > (* 2 2)",
+        ]
+    );
+}
+
+#[test]
+fn debug_form() {
+    let log = debug(
+        "(let (halve x) (if (debug) 0 (/ x 2))) (halve 10)",
+        vec![],
+        vec![Step::Into],
+    );
+    assert_eq!(log, ["Debug (halve 10)", "Step (/ 10 2)"]);
+}
+
+#[test]
+fn backtrace() {
+    let mut context = Context::default();
+    let input = "(let (f x) (+ (g x) 1) g (fn (x) (* x (h x))) h (fn (x) (/ x nope)))\n(f 2)";
+    let exprs = crisp::parse(input).unwrap();
+    let error = exprs
+        .into_iter()
+        .map(|it| context.eval(it))
+        .find_map(Result::err)
+        .unwrap();
+    let error = error.downcast_ref::<Backtrace>().unwrap();
+    assert_eq!(error.to_string(), "Invalid variable or function: nope");
+    let frames = error
+        .frames
+        .iter()
+        .map(|frame| {
+            let (line, column) = frame.span.location(input);
+            format!("{frame} {line}:{column}")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(frames, ["(h 2) 1:39", "(g 2) 1:15", "(f 2) 2:1"]);
+    let span = error.span.unwrap();
+    assert_eq!(&input[span.start..span.end], "(/ x nope)");
+    assert!(context.stack().is_empty());
+
+    let input = "(let (g x) (if x (backtrace)) (f x) (car (g x)))\n(f 2)";
+    let output = parse_and_eval(input, &mut context).unwrap().unwrap();
+    assert_eq!(output[1].to_string(), "(g 2)");
+}
+
+#[test]
+fn shadowing() {
+    let mut context = Context::default();
+    let input = "(let (backtrace) \"mine\" (debug x) (+ x 1)) (backtrace) (debug 1)";
+    let output = parse_and_eval(input, &mut context).unwrap().unwrap();
+    let output = output.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+    assert_eq!(output, ["nil", "\"mine\"", "2"]);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::eval;
+use crisp::{lint, parse, parse_and_eval, Context, Definition, Span, Symbol};
+
+const DEFINITIONS: &str = "\
+(let (triple x) \"Multiply x by 3\" (* x 3))
+(let limit 100 \"Largest allowed value\" (greeting) \"hello\")
+(let add (fn (a b) \"Sum of a and b\" (+ a b)))";
+
+#[test]
+fn docstrings() {
+    let mut context = Context::default();
+    eval(DEFINITIONS, &mut context);
+    assert_eq!(
+        eval(
+            "(doc 'triple) (doc \"limit\") (doc 'add) (doc 'greeting) (doc 'missing)
+             (triple limit) (greeting) (add 1 2)",
+            &mut context,
+        ),
+        [
+            "\"Multiply x by 3\"",
+            "\"Largest allowed value\"",
+            "\"Sum of a and b\"",
+            "nil",
+            "nil",
+            "300",
+            "\"hello\"",
+            "3",
+        ]
+    );
+
+    // Docstrings survive printing and parsing again
+    let printed = parse(DEFINITIONS)
+        .unwrap()
+        .iter()
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        printed[1],
+        "(let limit 100 \"Largest allowed value\" greeting (fn () \"hello\"))"
+    );
+    assert_eq!(
+        parse(&printed.join("\n")).unwrap(),
+        parse(DEFINITIONS).unwrap()
+    );
+}
+
+#[test]
+fn introspection() {
+    let mut context = Context::default();
+    eval(DEFINITIONS, &mut context);
+    assert_eq!(
+        eval(
+            "(arglist 'add) (arglist 'limit) (arglist 'map)
+             (defined-at 'triple) (defined-at 'map)
+             (bound? 'limit) (bound? map) (bound? 'missing)",
+            &mut context,
+        ),
+        [
+            "(a b)",
+            "nil",
+            "nil",
+            "(:start 0 :end 42)",
+            "nil",
+            "T",
+            "T",
+            "nil"
+        ]
+    );
+    assert_eq!(
+        parse_and_eval("(doc 1)", &mut context)
+            .unwrap()
+            .unwrap_err()
+            .to_string(),
+        "doc expects a symbol or string, found 1"
+    );
+
+    // Looking up names given as strings doesn't intern them
+    eval(
+        "(bound? \"never-bound\") (doc \"never-bound\") (arglist \"never-bound\")",
+        &mut context,
+    );
+    assert!(!context.is_bound("never-bound"));
+    assert!(Symbol::get("never-bound").is_none());
+}
+
+#[test]
+fn embedding() {
+    let mut context = Context::default();
+    eval(DEFINITIONS, &mut context);
+    context.document("map", "Call a function with elements of sequences");
+    assert_eq!(
+        eval("(doc 'map)", &mut context),
+        ["\"Call a function with elements of sequences\""]
+    );
+    assert_eq!(
+        context.definition("limit"),
+        Some(&Definition {
+            doc: Some(String::from("Largest allowed value")),
+            span: Some(Span::new(43, 101)),
+        })
+    );
+    assert_eq!(
+        context
+            .arglist("triple")
+            .unwrap()
+            .iter()
+            .map(|it| it.as_str())
+            .collect::<Vec<_>>(),
+        ["x"]
+    );
+
+    // Docstrings are skipped when linting
+    let input = format!("{DEFINITIONS}\n(add (triple limit) 2 3)");
+    let diagnostics = lint(&input, &Context::default())
+        .into_iter()
+        .map(|it| it.message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            "Unused binding: greeting",
+            "Expected maximum 2 arguments, got 3"
+        ]
+    );
+}
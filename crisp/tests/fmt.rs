@@ -0,0 +1,32 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn golden() {
+    for entry in std::fs::read_dir("tests/fmt").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().unwrap() != "crisp" {
+            continue;
+        }
+        let input = std::fs::read_to_string(&path).unwrap();
+        let golden = std::fs::read_to_string(path.with_extension("golden")).unwrap();
+        assert_eq!(crisp::format(&input).unwrap(), golden, "{path:?}");
+        assert_eq!(crisp::format(&golden).unwrap(), golden, "{path:?}");
+    }
+}
+
+#[test]
+fn check() {
+    let crisp = env!("CARGO_BIN_EXE_crisp");
+    let status = |file: &str| {
+        let path = Path::new("tests/fmt").join(file);
+        Command::new(crisp)
+            .args(["fmt", "--check"])
+            .arg(path)
+            .status()
+            .unwrap()
+            .code()
+    };
+    assert_eq!(status("functions.golden"), Some(0));
+    assert_eq!(status("functions.crisp"), Some(1));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{error, eval, trusted};
+use crisp::{Capability, Context};
+
+#[test]
+fn capabilities() {
+    let mut context = Context::default();
+    assert_eq!(
+        error("(read-file \"Cargo.toml\")", &mut context),
+        "read-file needs the read capability"
+    );
+    context.grant(Capability::Read);
+    assert!(context.is_granted(Capability::Read));
+    assert_eq!(eval("(file-exists? \"Cargo.toml\")", &mut context), ["T"]);
+    assert_eq!(
+        error("(make-dir \"target/never\")", &mut context),
+        "make-dir needs the write capability"
+    );
+    context.revoke(Capability::Read);
+    assert_eq!(
+        error("(env \"HOME\")", &mut context),
+        "env needs the env capability"
+    );
+    assert_eq!(
+        error("(list-dir \".\")", &mut context),
+        "list-dir needs the read capability"
+    );
+
+    // Paths are only strings
+    assert_eq!(
+        eval(
+            "(path-join \"a\" \"b\" \"c.txt\")
+             (path-parent \"a/b/c.txt\") (path-parent \"a\")
+             (path-name \"a/b/c.txt\")
+             (path-extension \"a/b/c.txt\") (path-extension \"a/b\")",
+            &mut context,
+        ),
+        [
+            "\"a/b/c.txt\"",
+            "\"a/b\"",
+            "nil",
+            "\"c.txt\"",
+            "\"txt\"",
+            "nil"
+        ]
+    );
+}
+
+#[test]
+fn disk() {
+    let directory = std::env::temp_dir().join(format!("crisp-fs-{}", std::process::id()));
+    let mut context = trusted();
+    context.define(
+        "dir",
+        crisp::Expr::Constant(crisp::Atom::String(
+            directory.to_string_lossy().into_owned(),
+        )),
+    );
+    assert_eq!(
+        eval(
+            "(let file (path-join dir \"notes\" \"a.txt\"))
+             (make-dir (path-join dir \"notes\"))
+             (write-file file \"hello\")
+             (append-file file \" world\")
+             (read-file file)
+             (write-file (path-join dir \"notes\" \"b.txt\") \"\")
+             (list-dir (path-join dir \"notes\"))
+             (delete-file file)
+             (file-exists? file)",
+            &mut context,
+        ),
+        [
+            "nil",
+            "nil",
+            "nil",
+            "nil",
+            "\"hello world\"",
+            "nil",
+            "(\"a.txt\" \"b.txt\")",
+            "nil",
+            "nil"
+        ]
+    );
+    assert!(error("(read-file file)", &mut context)
+        .ends_with("a.txt: No such file or directory (os error 2)"));
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn memory() {
+    let mut context = trusted();
+    context.use_memory_fs();
+    assert_eq!(
+        eval(
+            "(file-exists? \"Cargo.toml\")
+             (make-dir \"/a/b\")
+             (write-file \"a/b/../c.txt\" \"one\")
+             (append-file \"/a/c.txt\" \" two\")
+             (read-file \"a/c.txt\")
+             (list-dir \"a\")
+             (list-dir \"/\")
+             (delete-file \"a/c.txt\")
+             (list-dir \"a\")",
+            &mut context,
+        ),
+        [
+            "nil",
+            "nil",
+            "nil",
+            "nil",
+            "\"one two\"",
+            "(\"b\" \"c.txt\")",
+            "\"a\"",
+            "nil",
+            "\"b\""
+        ]
+    );
+    for (input, message) in [
+        ("(read-file \"a/missing\")", "a/missing doesn't exist"),
+        ("(read-file \"a\")", "a is a directory"),
+        ("(write-file \"x/y.txt\" \"\")", "x isn't a directory"),
+        ("(delete-file \"a/b\")", "a/b is a directory"),
+        ("(write-file \".\" \"\")", ". is a directory"),
+        ("(append-file \"a/..\" \"\")", "a/.. is a directory"),
+        ("(read-file \"/\")", "/ is a directory"),
+        ("(delete-file \".\")", ". is a directory"),
+        ("(list-dir \"a/c.txt\")", "a/c.txt isn't a directory"),
+        (
+            "(write-file \"a/d\" 1)",
+            "write-file expects a string, found 1",
+        ),
+    ] {
+        assert_eq!(error(input, &mut context), message);
+    }
+    assert_eq!(eval("(list-dir \".\")", &mut context), ["\"a\""]);
+}
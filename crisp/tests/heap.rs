@@ -0,0 +1,117 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Context, Expr};
+
+#[test]
+fn cycles() {
+    let mut context = Context::default();
+    eval(
+        "(let a (cell nil) b (cell a) c (cell nil))
+         (cell-set a b)
+         (cell-set c (list 1 2 c))",
+        &mut context,
+    );
+    assert_eq!(context.collect_garbage(), 0);
+    assert_eq!(context.heap_stats().live, 3);
+
+    eval("(let a nil b nil)", &mut context);
+    assert_eq!(context.collect_garbage(), 2);
+    eval("(let c nil)", &mut context);
+    assert_eq!(context.collect_garbage(), 1);
+    assert_eq!(context.heap_stats().live, 0);
+}
+
+#[test]
+fn functions() {
+    let mut context = Context::default();
+    // The cell is only reachable from the body of add
+    let output = eval(
+        "(let (adder c n) (cell-set c (+ (cell-get c) n)))
+         (let add (adder (cell 10)))
+         (add 5)",
+        &mut context,
+    );
+    assert_eq!(output[2], "15");
+    assert_eq!(context.collect_garbage(), 0);
+    assert_eq!(eval("(add 5)", &mut context), ["20"]);
+}
+
+#[test]
+fn automatic() {
+    let mut context = Context::default();
+    eval("(map cell (range 2000))", &mut context);
+    eval("(map cell (range 2000))", &mut context);
+    let stats = context.heap_stats();
+    assert_eq!(stats.collections, 2);
+    assert_eq!(stats.collected, 2000);
+    // The second batch was still in use when it was collected
+    assert_eq!(stats.live, 2000);
+    assert_eq!(stats.capacity, 4000);
+    assert_eq!(
+        eval("(gc-stats)", &mut context),
+        ["(:live 2000 :capacity 4000 :collections 2 :collected 2000)"]
+    );
+}
+
+// Cells made by earlier arguments of a call are still in use while the
+// later ones are evaluated
+#[test]
+fn arguments() {
+    let mut context = Context::default();
+    assert_eq!(
+        eval(
+            "(cell-get (car (list (cell 1) (car (map cell (range 1100))))))",
+            &mut context,
+        ),
+        ["1"]
+    );
+}
+
+#[test]
+fn collected() {
+    let mut context = Context::default();
+    let cell = context
+        .eval(crisp::parse("(cell 1)").unwrap().remove(0))
+        .unwrap();
+    assert!(matches!(cell, Expr::Cell(_)));
+    context.collect_garbage();
+    eval("(cell 2)", &mut context);
+    context.define("stale", cell);
+    let error = parse_and_eval("(cell-get stale)", &mut context)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Cell was collected");
+}
+
+#[test]
+fn pinned() {
+    let mut context = Context::default();
+    let cell = context
+        .eval(crisp::parse("(cell 1)").unwrap().remove(0))
+        .unwrap();
+    context.pin(&cell);
+    context.collect_garbage();
+    eval("(cell 2)", &mut context);
+    context.define("held", cell.clone());
+    assert_eq!(eval("(cell-get held)", &mut context), ["1"]);
+
+    // Once unpinned, it's only kept alive by the binding
+    context.unpin(&cell);
+    eval("(let held nil)", &mut context);
+    assert_eq!(context.collect_garbage(), 2);
+}
+
+#[test]
+fn image() {
+    let mut context = Context::default();
+    eval("(let a (cell nil)) (cell-set a (list 1 a))", &mut context);
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+    assert_eq!(
+        eval("(car (cell-get a)) (= a (cdr (cell-get a)))", &mut restored),
+        ["1", "T"]
+    );
+}
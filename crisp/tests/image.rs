@@ -0,0 +1,48 @@
+use crisp::{parse_and_eval, Context};
+
+#[test]
+fn roundtrip() {
+    let mut context = Context::default();
+    let input = "(let big 123456789012345678901234567890 items '(1 \"two\" :three))
+                 (let (sum x acc) (if (> x 0) (sum (- x 1) (+ acc x)) acc))";
+    parse_and_eval(input, &mut context).unwrap().unwrap();
+
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+
+    let output = parse_and_eval("(sum 10 0) big items", &mut restored)
+        .unwrap()
+        .unwrap();
+    let output = output.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        output,
+        ["55", "123456789012345678901234567890", "(1 \"two\" :three)"]
+    );
+}
+
+#[test]
+fn version() {
+    let image = r#"{ "version": 0, "environment": {} }"#;
+    let error = Context::default().load_image(image.as_bytes()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Expected image version 2, found version 0"
+    );
+}
+
+#[test]
+fn floats() {
+    let mut context = Context::default();
+    let input = "(let floats (list 1.5 inf (ln 0) nan))";
+    parse_and_eval(input, &mut context).unwrap().unwrap();
+
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+
+    let output = parse_and_eval("floats", &mut restored).unwrap().unwrap();
+    assert_eq!(output[0].to_string(), "(1.5 inf -inf nan)");
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Atom, Context, Expr};
+
+const SETTINGS: &str = r#"{"name": "crisp", "version": 1, "ratio": 0.5, "tags": ["lisp", true, false, null], "empty": {}}"#;
+
+fn context() -> Context {
+    let mut context = Context::default();
+    context.define(
+        "settings",
+        Expr::Constant(Atom::String(SETTINGS.to_string())),
+    );
+    context
+}
+
+#[test]
+fn parse() {
+    let mut context = context();
+    assert_eq!(
+        eval(
+            "(json-parse settings :keywords nil)
+             (json-parse settings)
+             (json-parse \"[1, -2, 2.5e3, 18446744073709551615]\")
+             (json-parse \"[]\")",
+            &mut context,
+        ),
+        [
+            "(\"name\" \"crisp\" \"version\" 1 \"ratio\" 0.5 \"tags\" (\"lisp\" T nil nil) \"empty\" )",
+            "(:name \"crisp\" :version 1 :ratio 0.5 :tags (\"lisp\" T nil nil) :empty )",
+            "(1 -2 2500.0 18446744073709551615)",
+            "",
+        ]
+    );
+}
+
+#[test]
+fn encode() {
+    let mut context = context();
+    assert_eq!(
+        eval(
+            "(json-encode '(:name \"crisp\" :tags (\"lisp\" 2.5 T nil) :nested (:a 1)))
+             (json-encode (list 1 2 3))
+             (json-encode :key)
+             (json-encode nil)
+             (json-encode (json-parse settings))",
+            &mut context,
+        ),
+        [
+            r#""{"name":"crisp","tags":["lisp",2.5,true,null],"nested":{"a":1}}""#,
+            r#""[1,2,3]""#,
+            r#"""key"""#,
+            r#""null""#,
+            r#""{"name":"crisp","version":1,"ratio":0.5,"tags":["lisp",true,null,null],"empty":[]}""#,
+        ]
+    );
+    assert_eq!(
+        eval("(json-encode '(:a (1 2)) :pretty 'T)", &mut context),
+        ["\"{\n  \"a\": [\n    1,\n    2\n  ]\n}\""]
+    );
+}
+
+#[test]
+fn roundtrip() {
+    let mut context = context();
+    for json in [
+        r#"{"name":"crisp","tags":["lisp",{"nested":[1,2]}],"empty":[]}"#,
+        r#"[]"#,
+        r#"[[],["a","b"],{"a":"b"}]"#,
+    ] {
+        context.define("json", Expr::Constant(Atom::String(json.to_string())));
+        assert_eq!(
+            eval("(json-encode (json-parse json))", &mut context),
+            [format!("\"{json}\"")]
+        );
+    }
+    assert_eq!(eval("(json-encode '())", &mut context), [r#""[]""#]);
+}
+
+#[test]
+fn errors() {
+    let mut context = context();
+    for (input, message) in [
+        ("(json-parse 1)", "json-parse expects a string, found 1"),
+        (
+            "(json-parse \"[1,\")",
+            "Invalid JSON: EOF while parsing a value at line 1 column 3",
+        ),
+        (
+            "(json-parse \"[]\" :pretty 'T)",
+            "json-parse doesn't take :pretty",
+        ),
+        (
+            "(json-encode 1 :pretty)",
+            "json-encode expects a value after :pretty",
+        ),
+        (
+            "(json-encode (fn (x) x))",
+            "json-encode can't encode (fn (x) x)",
+        ),
+        ("(json-encode (cell 1))", "json-encode can't encode <cell>"),
+        ("(json-encode nan)", "json-encode can't encode nan"),
+        (
+            "(json-encode (pow 2 100))",
+            "json-encode can't encode 1267650600228229401496703205376, it's too large",
+        ),
+    ] {
+        let error = parse_and_eval(input, &mut context).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
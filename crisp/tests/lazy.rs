@@ -0,0 +1,101 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Atom, Context, Error, Expr, Lazy, Sequence};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Lines of a buffer, counting how many have been read
+#[derive(Clone)]
+struct Lines {
+    lines: Vec<String>,
+    index: usize,
+    read: Arc<AtomicUsize>,
+}
+
+impl Sequence for Lines {
+    fn next(&mut self, _: &mut Context) -> Result<Option<Expr>, Error> {
+        let line = self.lines.get(self.index).cloned();
+        self.index += 1;
+        if line.is_some() {
+            self.read.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(line.map(|it| Expr::Constant(Atom::String(it))))
+    }
+}
+
+#[test]
+fn protocol() {
+    let read = Arc::new(AtomicUsize::new(0));
+    let lines = Lines {
+        lines: (1..=100).map(|it| format!("line {it}")).collect(),
+        index: 0,
+        read: read.clone(),
+    };
+    let mut context = Context::default();
+    context.define("buffer", Expr::Lazy(Lazy::new(lines)));
+
+    let output = eval(
+        "(let numbered (zip (range) buffer))
+         numbered
+         (take 2 (drop 1 numbered))
+         (car buffer)",
+        &mut context,
+    );
+    assert_eq!(
+        output,
+        [
+            "nil",
+            "<sequence>",
+            "((1 \"line 2\") (2 \"line 3\"))",
+            "\"line 1\""
+        ]
+    );
+    // Only what was asked for is read, and every walk starts from the top
+    assert_eq!(read.load(Ordering::Relaxed), 4);
+    assert_eq!(
+        eval(
+            "(reduce + (map (fn (line) (if line 1)) buffer))",
+            &mut context
+        ),
+        ["100"]
+    );
+}
+
+#[test]
+fn infinite() {
+    let mut context = Context::default();
+    let output = eval(
+        "(take 5 (filter (fn (x) (= (* (/ x 2) 2) x)) (range)))
+         (take-while (fn (x) (< x 100)) (iterate (fn (x) (* x 3)) 1))
+         (take 3 (map + (range) (drop 10 (range))))
+         (any? (fn (x) (> x 1000)) (range))
+         (take 10 (unfold (fn (x) (if (> x 0) (list x (- x 1)) nil)) 3))",
+        &mut context,
+    );
+    assert_eq!(
+        output,
+        ["(0 2 4 6 8)", "(1 3 9 27 81)", "(10 12 14)", "T", "(3 2 1)"]
+    );
+}
+
+#[test]
+fn image() {
+    let mut context = Context::default();
+    eval(
+        "(let numbers (range) nested (list 1 (range)) kept 5 held (cell (range)))",
+        &mut context,
+    );
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+    assert_eq!(
+        eval("kept (bound? 'numbers) (bound? 'nested)", &mut restored),
+        ["5", "nil", "nil"]
+    );
+    assert!(parse_and_eval("(cell-get held)", &mut restored)
+        .unwrap()
+        .is_err());
+}
@@ -0,0 +1,37 @@
+use crisp::{lint, Context};
+
+#[test]
+fn diagnostics() {
+    let input = "\
+(let (sum x acc) (if (> x 0) (sum (- x 1) (+ acc x)) acc))
+(sum 10 0 5)
+(let unused 10)
+(let car 5)
+(let (first x y) (if x (car x)))
+(first '(1 2) (cdr '(1 2) 3) missing)
+(+ defined 1)";
+    let mut context = Context::default();
+    crisp::parse_and_eval("(let defined 1)", &mut context)
+        .unwrap()
+        .unwrap();
+    let diagnostics = lint(input, &context)
+        .into_iter()
+        .map(|it| {
+            let (line, column) = it.span.location(input);
+            format!("{line}:{column} {} {}", it.severity, it.message)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            "2:1 error Expected maximum 2 arguments, got 3",
+            "3:6 warning Unused binding: unused",
+            "4:6 warning Shadowed builtin: car",
+            "5:15 warning Unused parameter: y",
+            "5:18 warning Missing else branch, fails when the predicate is false",
+            "6:1 error Expected maximum 2 arguments, got 3",
+            "6:15 error cdr expects 1 parameter, got 2",
+            "6:30 error Unbound symbol: missing",
+        ]
+    );
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Context, Output};
+
+#[test]
+fn format() {
+    let mut context = Context::default();
+    assert_eq!(
+        eval(
+            "(format \"{} + {} = {}\" 1 2 (+ 1 2))
+             (format \"{} and {:?}\" \"plain\" \"quoted\")
+             (format \"{{}} {}\" '(1 2))
+             (format \"no placeholders\")",
+            &mut context,
+        ),
+        [
+            "\"1 + 2 = 3\"",
+            "\"plain and \"quoted\"\"",
+            "\"{} (1 2)\"",
+            "\"no placeholders\"",
+        ]
+    );
+    for (input, message) in [
+        (
+            "(format \"{}\" 1 2)",
+            "format has 1 placeholder(s), found 2 argument(s)",
+        ),
+        (
+            "(println \"{} {}\" 1)",
+            "println has 2 placeholder(s), found 1 argument(s)",
+        ),
+        ("(format \"{x}\" 1)", "Invalid format string: {x}"),
+        ("(print 1)", "print expects a format string, found 1"),
+    ] {
+        let error = parse_and_eval(input, &mut context).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+#[test]
+fn ports() {
+    let mut context = Context::default();
+    assert!(matches!(
+        context.set_output(Output::Buffer(String::new())),
+        Output::Stdout
+    ));
+    assert_eq!(
+        eval(
+            "(print \"a\") (println \" {}\" 'b) (println) (println \"{:?}\" \"c\")",
+            &mut context,
+        ),
+        ["nil", "nil", "nil", "nil"]
+    );
+    assert_eq!(context.take_output(), "a b\n\n\"c\"\n");
+    assert_eq!(context.take_output(), "");
+
+    // Printing still works when the evaluation fails afterwards
+    assert!(parse_and_eval("(println \"before\") (car 1)", &mut context)
+        .unwrap()
+        .is_err());
+    assert_eq!(context.take_output(), "before\n");
+
+    let writer = Output::Writer(Box::new(Vec::new()));
+    assert!(matches!(context.set_output(writer), Output::Buffer(_)));
+    eval("(println \"to a writer\")", &mut context);
+    assert_eq!(context.take_output(), "");
+}
@@ -0,0 +1,105 @@
+use crisp::{parse, parse_recovering, Form};
+
+#[test]
+fn recover() {
+    let input = ") (+ 1 2)\n(let x (+ 1\n(fn x) \"a;b\"";
+    let recovered = parse_recovering(input);
+    let forms = recovered
+        .forms
+        .iter()
+        .map(|form| match form {
+            Form::Expr(expr, _) => format!("{expr}"),
+            Form::Error(span) => format!("error {:?}", &input[span.start..span.end]),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        forms,
+        [
+            "error \")\"",
+            "(+ 1 2)",
+            "error \"(let x (+ 1\\n\"",
+            "error \"(fn x)\"",
+            "\"a;b\""
+        ]
+    );
+    let errors = recovered
+        .errors
+        .iter()
+        .map(|error| (error.span.location(input), error.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ((1, 1), "unexpected ')'"),
+            ((2, 1), "unclosed '('"),
+            ((3, 5), "expected '('")
+        ]
+    );
+
+    // Errors stay inside the region they broke
+    let recovered = parse_recovering("(+ 1 2\n(+ 3 4)");
+    assert_eq!(recovered.forms.len(), 2);
+    assert!(matches!(recovered.forms[0], Form::Error(span) if span.start == 0 && span.end == 7));
+    let errors = recovered
+        .errors
+        .iter()
+        .map(|error| (error.span.start, error.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, [(0, "unclosed '('")]);
+}
+
+#[test]
+fn docstrings() {
+    for (input, output) in [
+        ("(let (f) \"a\" g 5)", "(let f (fn () \"a\") g 5)"),
+        (
+            "(let (f) \"a\" (g) h i 5)",
+            "(let f (fn () \"a\") g (fn () h) i 5)",
+        ),
+        ("(let (f) \"a\" \"b\")", "(let f (fn () \"b\") \"a\")"),
+        ("(let (f x) x \"a\" g 5)", "(let f (fn (x) x) \"a\" g 5)"),
+    ] {
+        let exprs = parse(input).unwrap();
+        assert_eq!(exprs[0].to_string(), output);
+    }
+
+    // Whether a string documents a function is decided without looking at
+    // the bindings after it, so long lets parse in linear time
+    let bindings = (0..2000)
+        .map(|it| format!("(f{it}) \"a\" (g{it}) h{it}"))
+        .collect::<Vec<_>>();
+    let exprs = parse(&format!("(let {} x 5)", bindings.join(" "))).unwrap();
+    assert!(exprs[0].to_string().ends_with("g1999 (fn () h1999) x 5)"));
+}
+
+#[test]
+fn comments() {
+    for (input, output) in [
+        ("(+ 1 #| #| nested |# |# 2)", "(+ 1 2)"),
+        ("(+ 1 ; one\n 2) ; two", "(+ 1 2)"),
+        ("\"a ; b #| c\"", "\"a ; b #| c\""),
+        ("(f 1 #_ 2)", "(f 1)"),
+        ("(f #_ #_ 1 2 3)", "(f 3)"),
+        ("(f #_(g 1) 2)", "(f 2)"),
+    ] {
+        let exprs = parse(input).unwrap();
+        assert_eq!(exprs[0].to_string(), output);
+    }
+
+    for (input, start, message) in [
+        ("(f #_)", 3, "datum comment needs a form"),
+        ("(f #_ ; nothing\n)", 3, "datum comment needs a form"),
+        ("#_", 0, "datum comment needs a form"),
+        ("(f #| open", 3, "unclosed block comment"),
+        ("#| #| inner |# outer", 0, "unclosed block comment"),
+    ] {
+        let recovered = parse_recovering(input);
+        let errors = recovered
+            .errors
+            .iter()
+            .map(|error| (error.span.start, error.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(errors, [(start, message)], "{input}");
+        assert!(parse(input).unwrap_err().to_string().contains(message));
+    }
+}
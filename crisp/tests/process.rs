@@ -0,0 +1,94 @@
+mod common;
+
+use common::{error, eval, trusted};
+use crisp::{Capability, Context};
+
+#[test]
+fn synchronous() {
+    let mut context = trusted();
+    assert_eq!(
+        eval(
+            "(call-process \"echo\" '(\"hello\" \"world\"))
+             (call-process \"cat\" nil :stdin \"piped\")
+             (shell-command \"echo $GREETING; echo oops >&2; exit 3\" :env '(\"GREETING\" \"hi\"))
+             (shell-command \"pwd\" :cwd \"/\")",
+            &mut context,
+        ),
+        [
+            "(:exit 0 :stdout \"hello world\n\" :stderr \"\")",
+            "(:exit 0 :stdout \"piped\" :stderr \"\")",
+            "(:exit 3 :stdout \"hi\n\" :stderr \"oops\n\")",
+            "(:exit 0 :stdout \"/\n\" :stderr \"\")",
+        ]
+    );
+}
+
+#[test]
+fn asynchronous() {
+    let mut context = trusted();
+    eval(
+        "(let lines (cell 0) last (cell nil) errors (cell nil) status (cell nil))
+         (let (line text) (if (cell-set last text) (cell-set lines (+ 1 (cell-get lines))) nil))
+         (let p (start-process \"sh\" '(\"-c\" \"echo one; echo two; echo bad >&2; exit 2\")
+           :output line
+           :error (fn (text) (cell-set errors text))
+           :exit (fn (code) (cell-set status code))))",
+        &mut context,
+    );
+    assert_eq!(
+        eval(
+            "(wait-process p) (cell-get lines) (cell-get last) (cell-get errors) (cell-get status)",
+            &mut context,
+        ),
+        ["2", "2", "\"two\n\"", "\"bad\n\"", "2"]
+    );
+    assert_eq!(context.poll_processes().unwrap(), 0);
+
+    // Hosts poll from their event loop instead of waiting
+    eval(
+        "(start-process \"echo\" '(\"later\") :output (fn (text) (cell-set last text)))",
+        &mut context,
+    );
+    while context.poll_processes().unwrap() > 0 {
+        std::thread::yield_now();
+    }
+    assert_eq!(eval("(cell-get last)", &mut context), ["\"later\n\""]);
+}
+
+#[test]
+fn errors() {
+    let mut context = Context::default();
+    assert_eq!(
+        error("(shell-command \"true\")", &mut context),
+        "shell-command needs the process capability"
+    );
+    context.grant(Capability::Process);
+    for (input, message) in [
+        (
+            "(call-process \"echo\" nil :cwd)",
+            "call-process expects a value after :cwd",
+        ),
+        (
+            "(call-process \"echo\" nil :output car)",
+            "call-process doesn't take :output",
+        ),
+        (
+            "(call-process \"echo\" '(1))",
+            "call-process expects a string, found 1",
+        ),
+        (
+            "(shell-command \"true\" :env '(\"A\"))",
+            "shell-command expects :env to be pairs of names and values",
+        ),
+        (
+            "(wait-process 0)",
+            "wait-process expects a running process, found 0",
+        ),
+    ] {
+        assert_eq!(error(input, &mut context), message);
+    }
+    assert!(
+        error("(call-process \"no-such-program\" nil)", &mut context)
+            .starts_with("call-process couldn't start no-such-program")
+    );
+}
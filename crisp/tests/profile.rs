@@ -0,0 +1,49 @@
+use crisp::{parse_and_eval, Context, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn profile() {
+    let mut context = Context::default();
+    assert_eq!(context.stop_profiling(), None);
+
+    let input = "(let (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))";
+    parse_and_eval(input, &mut context).unwrap().unwrap();
+    context.start_profiling();
+    parse_and_eval("(fib 10)", &mut context).unwrap().unwrap();
+    let profile = context.stop_profiling().unwrap();
+
+    let fib = profile.functions["fib"];
+    assert_eq!(fib.calls, 177);
+    assert_eq!(profile.functions["<"].calls, 177);
+    assert_eq!(profile.functions["+"].calls, 88);
+    assert!(fib.inclusive >= fib.exclusive);
+    assert!(fib.allocations > 0);
+
+    let report = profile.report();
+    let header = report.lines().next().unwrap();
+    assert_eq!(
+        header.split_whitespace().collect::<Vec<_>>(),
+        [
+            "function",
+            "calls",
+            "inclusive",
+            "ms",
+            "exclusive",
+            "ms",
+            "allocations"
+        ]
+    );
+    assert_eq!(report.lines().count(), 5);
+
+    let folded = profile.folded();
+    for line in folded.lines() {
+        let (stack, micros) = line.rsplit_once(' ').unwrap();
+        assert!(stack
+            .split(';')
+            .all(|it| profile.functions.contains_key(it)));
+        assert!(micros.parse::<u128>().is_ok());
+    }
+    assert!(folded.lines().any(|it| it.starts_with("fib;fib;fib;<")));
+}
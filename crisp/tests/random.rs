@@ -0,0 +1,77 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Context};
+
+const DRAWS: &str = "(list (random 100) (random -5 5) (random-float) (random-choice '(a b c)))
+                     (shuffle (range 10))";
+
+#[test]
+fn seeds() {
+    let mut a = Context::default();
+    let mut b = Context::default();
+    eval("(random-seed 7)", &mut a);
+    b.seed_random(7);
+    let first = eval(DRAWS, &mut a);
+    assert_eq!(first, eval(DRAWS, &mut b));
+    assert_ne!(first, eval(DRAWS, &mut a));
+
+    // Seeding again starts over
+    eval("(random-seed 7)", &mut a);
+    assert_eq!(first, eval(DRAWS, &mut a));
+    eval("(random-seed 8)", &mut a);
+    assert_ne!(first, eval(DRAWS, &mut a));
+}
+
+#[test]
+fn ranges() {
+    let mut context = Context::default();
+    eval("(random-seed 1)", &mut context);
+    assert_eq!(
+        eval(
+            "(every? (fn (x) (< -1 x 3)) (map (fn (x) (random (+ 3 (* x 0)))) (range 200)))
+             (every? (fn (x) (< -3 x 2)) (map (fn (x) (random (- x x 2) 2)) (range 200)))
+             (every? (fn (x) (< 0.999 x 1.5)) (map (fn (x) (random-float x 1.5)) (take 200 (iterate (fn (x) x) 1.0))))
+             (sort (shuffle (range 20)))
+             (random-choice nil)
+             (shuffle nil)",
+            &mut context,
+        ),
+        [
+            "T",
+            "T",
+            "T",
+            "(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19)",
+            "nil",
+            "nil"
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let mut context = Context::default();
+    for (input, message) in [
+        (
+            "(random 0)",
+            "random expects a range from 0 to 0 that isn't empty",
+        ),
+        (
+            "(random 5 2)",
+            "random expects a range from 5 to 2 that isn't empty",
+        ),
+        ("(random 1.5)", "random expects integers, found 1.5"),
+        (
+            "(random-float :a)",
+            "random-float expects a number, found :a",
+        ),
+        ("(shuffle 1)", "Expected a sequence, found 1"),
+        (
+            "(random-seed (pow 2 64))",
+            "random-seed expects a seed that fits in 64 bits, found 18446744073709551616",
+        ),
+    ] {
+        let error = parse_and_eval(input, &mut context).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
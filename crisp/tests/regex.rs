@@ -0,0 +1,89 @@
+mod common;
+
+use common::eval;
+use crisp::{parse_and_eval, Context};
+
+#[test]
+fn matching() {
+    let mut context = Context::default();
+    eval("(let email (regex \"(\\w+)@(\\w+)\"))", &mut context);
+    assert_eq!(
+        eval(
+            "email
+             (re-find email \"mail bob@example now\")
+             (re-find \"(a)|(b)\" \"b\")
+             (re-find \"x\" \"abc\")
+             (re-match \"a|ab\" \"ab\")
+             (re-match \"a+\" \"aab\")
+             (re-find-all \"\\d+\" \"1 22 333\")
+             (re-find-all \"\\d+\" \"none\")
+             (= (regex \"a+\") (regex \"a+\"))",
+            &mut context,
+        ),
+        [
+            "<regex \"(\\w+)@(\\w+)\">",
+            "((\"bob@example\" 5 16) (\"bob\" 5 8) (\"example\" 9 16))",
+            "((\"b\" 0 1) nil (\"b\" 0 1))",
+            "nil",
+            "(\"ab\" 0 2)",
+            "nil",
+            "((\"1\" 0 1) (\"22\" 2 4) (\"333\" 5 8))",
+            "nil",
+            "T",
+        ]
+    );
+}
+
+#[test]
+fn replacing() {
+    let mut context = Context::default();
+    assert_eq!(
+        eval(
+            "(re-replace \"(\\w+)@(\\w+)\" \"bob@example\" \"$2 at $1\")
+             (re-replace \"\\d+\" \"1 and 22\" (fn (m) (to-string (* 2 (parse-number (car (car m)))))))
+             (re-split \",\\s*\" \"a, b,c\")
+             (re-split \",\" \"\")",
+            &mut context,
+        ),
+        [
+            "\"example at bob\"",
+            "\"2 and 44\"",
+            "(\"a\" \"b\" \"c\")",
+            "\"\"",
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let mut context = Context::default();
+    for (input, message) in [
+        ("(regex 1)", "regex expects a regex or a string, found 1"),
+        ("(re-find \"a\" :a)", "re-find expects a string, found :a"),
+        (
+            "(re-replace \"a\" \"a\" (fn (m) (if m 1 0)))",
+            "re-replace expects a string, found 1",
+        ),
+    ] {
+        let error = parse_and_eval(input, &mut context).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+    let error = parse_and_eval("(regex \"(\")", &mut context)
+        .unwrap()
+        .unwrap_err();
+    assert!(error.to_string().starts_with("Invalid regex"));
+}
+
+#[test]
+fn image() {
+    let mut context = Context::default();
+    eval("(let digits (regex \"\\d+\"))", &mut context);
+    let mut image = Vec::new();
+    context.save_image(&mut image).unwrap();
+    let mut restored = Context::default();
+    restored.load_image(image.as_slice()).unwrap();
+    assert_eq!(
+        eval("(re-find digits \"ab12\")", &mut restored),
+        ["(\"12\" 2 4)"]
+    );
+}
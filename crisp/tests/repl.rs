@@ -0,0 +1,32 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Every transcript in DOCS.md should come out of the REPL as written
+#[test]
+fn transcripts() {
+    let file = std::fs::read_to_string("../DOCS.md").unwrap();
+    for block in file.split("\n\n").filter(|it| it.contains("```")) {
+        let amount = block.lines().count();
+        let inner = block.lines().skip(1).take(amount - 2).collect::<Vec<_>>();
+        let inputs = inner.iter().step_by(2).map(|it| &it[3..]);
+        let outputs = inner.iter().skip(1).step_by(2).copied();
+
+        let mut repl = Command::new(env!("CARGO_BIN_EXE_crisp"))
+            .env_remove("HOME")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = repl.stdin.take().unwrap();
+        for input in inputs {
+            writeln!(stdin, "{input}").unwrap();
+        }
+        drop(stdin);
+        let output = repl.wait_with_output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            stdout.lines().collect::<Vec<_>>(),
+            outputs.collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::process::{Command, Output};
+
+fn crisp(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_crisp"))
+        .args(args)
+        .env("CRISP_TEST", "value")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn eval() {
+    let output = crisp(&["run", "--eval", "(cdr args)", "first", "second"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\"second\"\n");
+
+    let output = crisp(&["--eval", "(env \"CRISP_TEST\")"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\"value\"\n");
+}
+
+#[test]
+fn error() {
+    let output = crisp(&["run", "tests/run/error.crisp"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tests/run/error.crisp:4:1: Evaluation error: Invalid variable or function: missing\n"
+    );
+}
+
+#[test]
+fn backtrace() {
+    let output = crisp(&["run", "tests/run/backtrace.crisp"]);
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "\
+tests/run/backtrace.crisp:3:12: Evaluation error: Invalid variable or function: nope
+    in (h 2) at tests/run/backtrace.crisp:2:17
+    in (g 2) at tests/run/backtrace.crisp:1:15
+    in (f 2) at tests/run/backtrace.crisp:5:1
+"
+    );
+}
+
+#[test]
+fn print() {
+    let output = crisp(&["--eval", "(println \"{} {}\" (car args) 2)", "one"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "one 2\nnil\n");
+}
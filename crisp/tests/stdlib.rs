@@ -0,0 +1,238 @@
+use crisp::{parse_and_eval, Context};
+
+fn eval(input: &str) -> Vec<String> {
+    let mut context = Context::default();
+    parse_and_eval(input, &mut context)
+        .unwrap()
+        .unwrap()
+        .iter()
+        .map(|it| it.to_string())
+        .collect()
+}
+
+#[test]
+fn higher_order() {
+    let output = eval(
+        "(let (square x) (* x x))
+         (map square '(1 2 3))
+         (map + '(1 2 3) '(10 20 30))
+         (filter (fn (x) (> x 2)) (range 6))
+         (reduce + (range 5))
+         (reduce + 100 '(1 2))
+         (fold * 1 '(1 2 3 4))
+         (apply + 1 2 '(3 4))
+         (apply + '()) (reduce + '()) (reduce * '()) (apply + '(1.5 2.5))
+         (funcall car '(5 6))
+         (any? (fn (x) (= x 2)) '(1 2 3))
+         (every? (fn (x) (> x 0)) '(1 2 -3))
+         (map car '((1 2) (3 4)))",
+    );
+    assert_eq!(
+        output,
+        [
+            "nil",
+            "(1 4 9)",
+            "(11 22 33)",
+            "(3 4 5)",
+            "10",
+            "103",
+            "24",
+            "10",
+            "0",
+            "0",
+            "1",
+            "4.0",
+            "5",
+            "T",
+            "nil",
+            "(1 3)"
+        ]
+    );
+}
+
+#[test]
+fn sequences() {
+    let output = eval(
+        "(take 2 \"hello\")
+         (drop 3 (range 5))
+         (take 10 nil)
+         (zip '(1 2 3) '(:a :b))
+         (range 10 0 -3)
+         (sort '(3 1 2))
+         (sort-by (fn (x) (- 0 x)) '(3 1 2))
+         (group-by (fn (x) (> x 2)) '(1 3 2 4))",
+    );
+    assert_eq!(
+        output,
+        [
+            "('h' 'e')",
+            "(3 4)",
+            "nil",
+            "((1 :a) (2 :b))",
+            "(10 7 4 1)",
+            "(1 2 3)",
+            "(3 2 1)",
+            "((nil (1 2)) (T (3 4)))",
+        ]
+    );
+}
+
+#[test]
+fn integers() {
+    let output = eval(
+        "(mod 7 3) (mod -7 3) (mod 7 -3)
+         (pow 2 100) (pow 10 0)
+         (pow 1 100000000) (pow 0 100000000) (pow -1 100000001) (pow 0 0)
+         (gcd 12 18 27) (lcm 4 6)
+         (bit-and 12 10) (bit-or 12 10) (bit-xor 12 10) (bit-and -1 255)
+         (shift-left 1 64) (shift-right -16 2)
+         (to-string 255 16) (to-string -10 2) (to-string :a) (to-string \"a\")
+         (parse-number \"ff\" 16) (parse-number \"-0x1_0\") (parse-number \"2.5\")
+         (parse-number \"12a\") (parse-number \"z\" 36)",
+    );
+    assert_eq!(
+        output,
+        [
+            "1",
+            "2",
+            "-2",
+            "1267650600228229401496703205376",
+            "1",
+            "1",
+            "0",
+            "-1",
+            "1",
+            "3",
+            "12",
+            "8",
+            "14",
+            "6",
+            "255",
+            "18446744073709551616",
+            "-4",
+            "\"ff\"",
+            "\"-1010\"",
+            "\":a\"",
+            "\"a\"",
+            "255",
+            "-16",
+            "2.5",
+            "nil",
+            "35",
+        ]
+    );
+}
+
+#[test]
+fn literals() {
+    assert_eq!(
+        eval("0xff 0b1010 0o17 -0x10 1_000_000 1_000.5 (+ 0xa 0b1)"),
+        ["255", "10", "15", "-16", "1000000", "1000.5", "11"]
+    );
+}
+
+#[test]
+fn floats() {
+    assert_eq!(
+        eval("1e3 .5 -2.5e-3 1.5E+2 inf -inf nan (+ .5 .25) 3.0"),
+        ["1000.0", "0.5", "-0.0025", "150.0", "inf", "-inf", "nan", "0.75", "3.0"]
+    );
+    // Floats can't be written with a sign after the dot, and inf and nan
+    // only stand on their own
+    assert!(crisp::parse("1.-5").is_err());
+    assert!(crisp::parse("1.").is_err());
+    for input in ["1.5e", "2.5x", "1e5e", "1.5.3"] {
+        assert!(crisp::parse(input).is_err(), "{input}");
+    }
+    assert_eq!(eval("(let info 1 nanny 2) (+ info nanny)"), ["nil", "3"]);
+    // inf and nan are floats, so they can't be bound
+    for (input, name) in [
+        ("(let nan 3)", "nan"),
+        ("(let (f nan) nan)", "nan"),
+        ("(fn (inf) inf)", "inf"),
+        ("(let 5 3)", "5"),
+    ] {
+        let recovered = crisp::parse_recovering(input);
+        assert_eq!(
+            recovered.errors[0].message,
+            format!("{name} is a number and can't be used as a name")
+        );
+    }
+}
+
+#[test]
+fn math() {
+    let output = eval(
+        "(sqrt 16) (exp 0) (ln 1) (log 1000) (log 8 2)
+         (sin 0) (cos 0) (atan2 1 1)
+         (floor 2.7) (ceil 2.1) (round 2.5) (round -2.5) (truncate -2.7) (floor 5)
+         (float 3) (int 3.9) (format-float 3.14159 2) (format-float 2 3)",
+    );
+    assert_eq!(
+        output,
+        [
+            "4.0",
+            "1.0",
+            "0.0",
+            "3.0",
+            "3.0",
+            "0.0",
+            "1.0",
+            "0.7853981633974483",
+            "2",
+            "3",
+            "3",
+            "-3",
+            "-2",
+            "5",
+            "3.0",
+            "3",
+            "\"3.14\"",
+            "\"2.000\"",
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let mut context = Context::default();
+    for (input, message) in [
+        ("(map car)", "map expects at least 2 parameters, got 1"),
+        ("(range 1 2 0)", "range expects a step other than 0"),
+        ("(sort '(1 \"a\"))", "Can't compare 1 with \"a\""),
+        ("(car 5)", "Expected a sequence, found 5"),
+        ("(mod 5 0)", "mod expects a divisor other than 0"),
+        ("(bit-and 1 1.5)", "bit-and expects integers, found 1.5"),
+        (
+            "(pow 2 100000000000000)",
+            "pow would make a number with more than 16777216 bits",
+        ),
+        (
+            "(shift-left 1 100000000000000)",
+            "shift-left would make a number with more than 16777216 bits",
+        ),
+        ("(to-string 10 1)", "Expected a radix from 2 to 36, found 1"),
+        ("(sqrt :a)", "sqrt expects a number, found :a"),
+        ("(floor nan)", "Can't turn nan into an integer"),
+        (
+            "(format-float 1.5 18)",
+            "format-float expects at most 17 digits, found 18",
+        ),
+    ] {
+        let error = parse_and_eval(input, &mut context).unwrap().unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+#[test]
+fn shadowing() {
+    let output = eval(
+        "(let (range x) (+ x 42))
+         (range 3)
+         (let (format x) x)
+         (format 1)
+         (map format '(1 2))
+         (list 1 2)",
+    );
+    assert_eq!(output, ["nil", "45", "nil", "1", "(1 2)", "(1 2)"]);
+}
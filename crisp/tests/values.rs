@@ -0,0 +1,31 @@
+use crisp::{parse_and_eval, Atom, Context, Expr, List, Symbol};
+
+#[test]
+fn interning() {
+    let name = String::from("interned");
+    let a = Symbol::new(&name);
+    let b = Symbol::new("interned");
+    assert_eq!(a, b);
+    assert!(std::ptr::eq(a.as_str(), b.as_str()));
+    assert_ne!(a, Symbol::new("other"));
+}
+
+#[test]
+fn sharing() {
+    let items = (0..10_000)
+        .map(|it| Expr::Constant(Atom::Number(it.into())))
+        .collect::<List>();
+    let tail = items.tail(1);
+    assert_eq!(tail.len(), 9_999);
+    assert!(std::ptr::eq(&items[1], &tail[0]));
+
+    let mut context = Context::default();
+    context.define("big", Expr::Quote(items.clone()));
+    let output = parse_and_eval("(let (rest xs) (cdr xs)) (rest big)", &mut context)
+        .unwrap()
+        .unwrap();
+    match &output[1] {
+        Expr::Quote(rest) => assert!(std::ptr::eq(&items[1], &rest[0])),
+        it => panic!("Expected a list, found {it}"),
+    }
+}
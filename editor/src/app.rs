@@ -1,20 +1,9 @@
 use crate::highlight::highlight;
-use crisp::Context;
+use crisp::{Context, Output};
 use eframe::{egui::*, epi};
 
 const FONT_SIZE: f32 = 44.0;
-
-fn strip_comment(input: &str) -> &str {
-    input
-        .find(";")
-        .map(|it| &input[..it])
-        .unwrap_or(input)
-        .trim()
-}
-
-fn sanitize(input: &str) -> String {
-    input.lines().map(strip_comment).collect::<Vec<_>>().join("\n")    
-}
+const SESSION_KEY: &str = "session";
 
 #[derive(Default)]
 pub struct Editor {
@@ -23,12 +12,6 @@ pub struct Editor {
     output: String,
 }
 
-impl Editor {
-    fn input(&self) -> String {
-        sanitize(&self.input)
-    }
-}
-
 impl epi::App for Editor {
     fn name(&self) -> &str {
         "crispmacs"
@@ -38,7 +21,17 @@ impl epi::App for Editor {
         Vec2::new(2560.0, 1440.0)
     }
 
-    fn setup(&mut self, ctx: &CtxRef, _: &epi::Frame, _: Option<&dyn epi::Storage>) {
+    fn setup(&mut self, ctx: &CtxRef, _: &epi::Frame, storage: Option<&dyn epi::Storage>) {
+        // Printed text goes to the result window along with the values
+        self.context.set_output(Output::Buffer(String::new()));
+
+        // Restore the environment from the last session
+        if let Some(image) = storage.and_then(|it| it.get_string(SESSION_KEY)) {
+            if let Err(error) = self.context.load_image(image.as_bytes()) {
+                self.output = format!("Could not restore session: {error}");
+            }
+        }
+
         // Proper font
         let mut fonts = FontDefinitions::default();
         for font in fonts.family_and_size.iter_mut() {
@@ -51,29 +44,62 @@ impl epi::App for Editor {
         ctx.set_visuals(Visuals::light());
     }
 
+    fn save(&mut self, storage: &mut dyn epi::Storage) {
+        let mut image = Vec::new();
+        match self.context.save_image(&mut image) {
+            Ok(()) => storage.set_string(SESSION_KEY, String::from_utf8_lossy(&image).into_owned()),
+            Err(error) => self.output = format!("Could not save session: {error}"),
+        }
+    }
+
     fn update(&mut self, ctx: &CtxRef, _: &epi::Frame) {
         TopBottomPanel::top("Menu").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.expand_to_include_y(FONT_SIZE + 20.0);
                 if ui.button("Parse").clicked() {
-                    match crisp::parse(&self.input()) {
-                        Ok(exprs) => self.output = format!("{:#?}", exprs),
-                        Err(error) => self.output = format!("Parsing error: {error}"),
+                    let recovered = crisp::parse_recovering(&self.input);
+                    let errors = recovered.errors.iter().map(|error| {
+                        let (line, column) = error.span.location(&self.input);
+                        format!("Parsing error at {line}:{column}: {}", error.message)
+                    });
+                    let exprs = recovered.exprs().collect::<Vec<_>>();
+                    self.output = errors
+                        .chain(std::iter::once(format!("{:#?}", exprs)))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                }
+
+                if ui.button("Lint").clicked() {
+                    self.output = crisp::lint(&self.input, &self.context)
+                        .into_iter()
+                        .map(|it| {
+                            let (line, column) = it.span.location(&self.input);
+                            format!("{line}:{column}: {}: {}", it.severity, it.message)
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                }
+
+                if ui.button("Format").clicked() {
+                    match crisp::format(&self.input) {
+                        Ok(input) => self.input = input,
+                        Err(error) => self.output = format!("Formatting error: {error}"),
                     }
                 }
 
                 if ui.button("Evaluate").clicked() {
-                    match crisp::parse_and_eval(&self.input(), &mut self.context) {
-                        Ok(Ok(exprs)) => {
-                            self.output = exprs
-                                .into_iter()
-                                .map(|it| format!("{it}"))
-                                .collect::<Vec<_>>()
-                                .join("\n")
-                        }
-                        Ok(Err(error)) => self.output = format!("Evaluation error: {error}"),
-                        Err(error) => self.output = format!("Parsing error: {error}"),
-                    }
+                    let result = crisp::parse_and_eval(&self.input, &mut self.context);
+                    let printed = self.context.take_output();
+                    let output = match result {
+                        Ok(Ok(exprs)) => exprs
+                            .into_iter()
+                            .map(|it| format!("{it}"))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        Ok(Err(error)) => format!("Evaluation error: {error}"),
+                        Err(error) => format!("Parsing error: {error}"),
+                    };
+                    self.output = printed + &output;
                 }
             });
         });